@@ -0,0 +1,102 @@
+use futures::future::{BoxFuture, FutureExt};
+use serde_json::Value;
+use std::path::PathBuf;
+
+use crate::{FetchRoute, HtmlFetcher, ScrapeState, ScraperContext};
+
+/// Canned responses read from `MOCK_FIXTURES_DIR`, standing in for every
+/// network call so a scrape can be driven offline:
+///
+/// - `html/<key>.html` – origin page for the fetch approaches
+/// - `worker/<key>.json` – Cloudflare worker response
+/// - `shopify/<key>.json` – Shopify `<product-url>.json` response
+/// - `gemini_extraction/<key>.json`, `gemini_fast/<key>.json`,
+///   `gemini_classification/<key>.json` – raw Gemini API responses
+/// - `serpapi/<engine>.json` – SerpAPI response per engine
+/// - `robots/<key>.txt` – the host's robots.txt for `respect_robots` (keyed by
+///   the robots.txt url; missing means no rules)
+///
+/// `<key>` is `fixture_key(url)`; each directory may also hold a
+/// `default.json`/`default.html` used when no keyed file exists. A missing
+/// fixture behaves like a failed request.
+#[derive(Debug, Clone)]
+pub(crate) struct Fixtures {
+    dir: PathBuf,
+}
+
+impl Fixtures {
+    pub(crate) fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub(crate) fn html(&self, url: &str) -> Option<String> {
+        self.read("html", &fixture_key(url), "html")
+    }
+
+    pub(crate) fn robots(&self, robots_url: &str) -> Option<String> {
+        self.read("robots", &fixture_key(robots_url), "txt")
+    }
+
+    pub(crate) fn json(&self, kind: &str, key: &str) -> Option<Value> {
+        let body = self.read(kind, &fixture_key(key), "json")?;
+        match serde_json::from_str(&body) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                log::warn!("[mock] invalid fixture json in {}/{}: {}", kind, key, e);
+                None
+            }
+        }
+    }
+
+    fn read(&self, kind: &str, key: &str, ext: &str) -> Option<String> {
+        let dir = self.dir.join(kind);
+        let path = [dir.join(format!("{}.{}", key, ext)), dir.join(format!("default.{}", ext))]
+            .into_iter()
+            .find(|path| path.is_file());
+        match path {
+            Some(path) => std::fs::read_to_string(&path).ok(),
+            None => {
+                log::info!("[mock] no fixture {}/{}.{}", kind, key, ext);
+                None
+            }
+        }
+    }
+}
+
+/// Mock mode's page source: every route serves `html/<key>.html`. The proxy
+/// route still needs a proxy configured, as in a live run.
+impl HtmlFetcher for Fixtures {
+    fn fetch<'a>(
+        &'a self,
+        route: FetchRoute,
+        url: &'a str,
+        state: &'a ScrapeState,
+        ctx: &'a ScraperContext,
+    ) -> BoxFuture<'a, Option<String>> {
+        let html = if route == FetchRoute::Proxy && ctx.proxy_pool.is_none() {
+            None
+        } else {
+            self.html(url)
+        };
+        if let Some(html) = &html {
+            state.record_bytes(html.len());
+        }
+        futures::future::ready(html).boxed()
+    }
+}
+
+/// File-name-safe key for a URL: scheme dropped, lowercased, runs of anything
+/// but ASCII letters/digits collapsed to `_`.
+/// "https://shop.com/p/Tee-1?c=red" → "shop_com_p_tee_1_c_red".
+pub(crate) fn fixture_key(url: &str) -> String {
+    let rest = url.split_once("://").map(|(_, rest)| rest).unwrap_or(url);
+    let mut key = String::with_capacity(rest.len());
+    for c in rest.chars() {
+        if c.is_ascii_alphanumeric() {
+            key.push(c.to_ascii_lowercase());
+        } else if !key.ends_with('_') {
+            key.push('_');
+        }
+    }
+    key.trim_matches('_').to_string()
+}
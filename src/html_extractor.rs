@@ -3,32 +3,133 @@ use scraper::{ElementRef, Html, Selector};
 use serde_json::{json, Map, Value};
 use url::Url;
 
+const DEFAULT_MAX_IMG_SCAN: usize = 50;
+const DEFAULT_EXTRACTOR_MAX_TOKENS: usize = 50_000;
+const SHIPPING_INFO_MAX_CHARS: usize = 300;
+const MICRODATA_VALUE_MAX_CHARS: usize = 500;
+
+/// Substrings that mark an image URL as non-product; tuned per deployment via
+/// IMAGE_EXCLUDE_PATTERNS / IMAGE_EXCLUDE_REMOVE.
+const DEFAULT_EXCLUDED_IMAGE_PATTERNS: &[&str] = &[
+    "logo", "icon", "favicon", "sprite", "loading", "placeholder",
+    "social", "facebook", "twitter", "instagram", "youtube",
+    "payment", "visa", "mastercard", "paypal", "stripe",
+    "shipping", "delivery", "banner", "advertisement",
+];
+
+/// Class/id patterns for the element wrapping a product's gallery, most specific first.
+const GALLERY_CONTAINER_SELECTORS: &[&str] = &[
+    "[class*=\"product-gallery\"]",
+    "[class*=\"product-images\"]",
+    "[class*=\"product-media\"]",
+    "[class*=\"gallery\"]",
+    "[id*=\"gallery\"]",
+    "[class*=\"carousel\"]",
+    "[itemtype*=\"schema.org/Product\"]",
+];
+
 pub struct ProductDataExtractor {
+    // Token budget past which trim_content runs (EXTRACTOR_MAX_TOKENS).
     max_tokens: usize,
-    token_char_ratio: usize,
+    // Fixed chars-per-token estimate (with_token_char_ratio, default
+    // TOKEN_CHAR_RATIO); None uses the word/punctuation estimate.
+    token_char_ratio: Option<f64>,
+    // Cap on <img> elements scored per scan (MAX_IMG_SCAN).
+    max_img_scan: usize,
+    excluded_image_patterns: Vec<String>,
+    // Keep <img> candidates in page order instead of by score (IMAGES_PRESERVE_DOM_ORDER).
+    preserve_dom_order: bool,
+    // Repair script boundaries before parsing (SANITIZE_HTML); costs a pass over the page.
+    sanitize_html: bool,
 }
 
 impl ProductDataExtractor {
     pub fn new(max_tokens: usize) -> Self {
         Self {
             max_tokens,
-            token_char_ratio: 4,
+            token_char_ratio: std::env::var("TOKEN_CHAR_RATIO")
+                .ok()
+                .and_then(|v| v.parse::<f64>().ok())
+                .filter(|ratio| *ratio > 0.0),
+            max_img_scan: std::env::var("MAX_IMG_SCAN")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_MAX_IMG_SCAN),
+            excluded_image_patterns: excluded_image_patterns_from_env(),
+            preserve_dom_order: matches!(
+                std::env::var("IMAGES_PRESERVE_DOM_ORDER").as_deref(),
+                Ok("1" | "true" | "yes")
+            ),
+            sanitize_html: matches!(
+                std::env::var("SANITIZE_HTML").as_deref(),
+                Ok("1" | "true" | "yes")
+            ),
         }
     }
 
+    /// Fixed characters-per-token for the estimate that decides when to trim
+    /// against `max_tokens` (e.g. 2.5 for CJK-heavy pages); None, or a ratio
+    /// that isn't positive, uses the word/punctuation estimate. Overrides
+    /// TOKEN_CHAR_RATIO.
+    pub fn with_token_char_ratio(mut self, ratio: Option<f64>) -> Self {
+        self.token_char_ratio = ratio.filter(|ratio| *ratio > 0.0);
+        self
+    }
+
+    /// `new` with the token budget from EXTRACTOR_MAX_TOKENS (default 50k).
+    pub fn from_env() -> Self {
+        Self::new(
+            std::env::var("EXTRACTOR_MAX_TOKENS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_EXTRACTOR_MAX_TOKENS),
+        )
+    }
+
     pub fn extract_product_data(&self, url: &str, html: &str) -> Value {
+        let sanitized;
+        let html = if self.sanitize_html {
+            sanitized = sanitize_script_boundaries(html);
+            sanitized.as_str()
+        } else {
+            html
+        };
         let document = Html::parse_document(html);
 
         let structured_data = self.extract_structured_data(&document);
         let inline_json_images = self.extract_inline_json(&document);
+        let app_state = self.extract_app_state(&document);
+        let data_layer = self.extract_data_layer(&document);
 
         let price_signals = self.extract_price_signals(&document);
+        let variant_prices = self.extract_variant_prices(&document);
+        let price_gated = self.detect_price_gate(&document);
+        let microdata_price = self.extract_microdata_price(&document);
+        let platform = self.detect_platform(&document, html);
+        let sale_markup = self.extract_sale_markup(&document);
+        let size_chart = self.extract_size_chart(&document);
+        let shipping_info = self.extract_shipping_info(&document);
+        let sizes = self.extract_sizes(&document);
+        let color_swatches = self.extract_color_swatches(&document);
         let text_content = self.extract_text_content(&document);
 
+        // AMP pages render images through <amp-img> and usually point at the
+        // canonical non-AMP page.
+        let is_amp = self.is_amp_page(&document);
+        let canonical_url = self.extract_canonical_url(&document, url);
+        let alternate_urls = self.extract_alternate_urls(&document, url);
+
         let mut all_images: Vec<String> = Vec::new();
 
-        // Method 1: smart filtering from <img> tags
-        let img_tag_images = self.filter_product_images(&document, url);
+        // Method 1: smart filtering from <img> (and <amp-img>) tags
+        let name_hints = image_name_hints(&structured_data);
+        let img_tag_images = self.filter_product_images(&document, url, is_amp, &name_hints);
+        let mut img_tag_meta: std::collections::HashMap<String, (String, i32)> = std::collections::HashMap::new();
+        for img in &img_tag_images {
+            img_tag_meta
+                .entry(img.src.clone())
+                .or_insert_with(|| (img.alt.clone(), img.score));
+        }
         all_images.extend(img_tag_images.into_iter().map(|img| img.src));
 
         // Method 2: JSON-LD images
@@ -42,28 +143,108 @@ impl ProductDataExtractor {
         let preload_images = self.extract_preload_images(&document, url);
         all_images.extend(preload_images);
 
-        // Deduplicate while preserving order
-        let mut seen = std::collections::HashSet::new();
-        let mut unique_images = Vec::new();
+        // Every size seen for each image (collected URLs plus srcset candidates),
+        // grouped before dedup collapses them.
+        let image_variants = group_image_variants(&all_images, &self.extract_srcset_candidates(&document, url));
+
+        // GIFs are usually spinners/badges: keep them only when allowed or when
+        // they're all we found (animated product spins).
+        let all_images = crate::keep_gifs_if_needed(all_images, |u: &String| u.as_str());
+
+        // Deduplicate by canonical_image_key while preserving order, so size
+        // variants of one shot count once; the widest variant's URL is kept.
+        let mut seen: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
+        // (kept url, its width hint, first-seen url)
+        let mut unique_images: Vec<(String, u32, String)> = Vec::new();
         for img_url in all_images {
-            if !img_url.is_empty() && !seen.contains(&img_url) {
-                seen.insert(img_url.clone());
-                unique_images.push(img_url);
+            if img_url.is_empty() {
+                continue;
+            }
+            let key = canonical_image_key(&img_url);
+            let width = image_width_hint(&img_url).unwrap_or(0);
+            match seen.get(&key) {
+                Some(&idx) => {
+                    let kept = &mut unique_images[idx];
+                    if width > kept.1 {
+                        kept.0 = img_url;
+                        kept.1 = width;
+                    }
+                }
+                None => {
+                    seen.insert(key, unique_images.len());
+                    unique_images.push((img_url.clone(), width, img_url));
+                }
             }
         }
 
-        // Convert to list of dicts with src and metadata (alt/score left empty)
+        // Convert to list of dicts with src and metadata; alt/score are only
+        // known for images found as <img> tags (under any of their sizes).
         let images: Vec<Value> = unique_images
             .into_iter()
-            .map(|u| json!({ "src": u, "alt": "", "score": 0 }))
+            .map(|(u, _, first_seen)| {
+                let (alt, score) = img_tag_meta
+                    .get(&u)
+                    .or_else(|| img_tag_meta.get(&first_seen))
+                    .cloned()
+                    .unwrap_or_default();
+                json!({ "src": u, "alt": alt, "score": score })
+            })
             .collect();
 
+        // Recorded before trim_content so callers can tell how much of the gallery was cut.
+        let total_image_count = images.len();
+
         let mut output = Map::new();
         output.insert("url".to_string(), Value::String(url.to_string()));
         output.insert("structured_data".to_string(), structured_data);
         output.insert("price_signals".to_string(), Value::Array(price_signals.into_iter().map(Value::String).collect()));
+        output.insert("variant_prices".to_string(), Value::Array(variant_prices));
+        output.insert("price_gated".to_string(), Value::Bool(price_gated));
+        output.insert("microdata_price".to_string(), microdata_price.unwrap_or(Value::Null));
+        output.insert("platform".to_string(), Value::String(platform.to_string()));
+        output.insert("app_state".to_string(), app_state.unwrap_or(Value::Null));
+        output.insert("data_layer".to_string(), data_layer.unwrap_or(Value::Null));
+        output.insert("sale_markup".to_string(), sale_markup.unwrap_or(Value::Null));
+        output.insert("size_chart".to_string(), size_chart.unwrap_or(Value::Null));
+        output.insert(
+            "shipping_info".to_string(),
+            shipping_info.map(Value::String).unwrap_or(Value::Null),
+        );
+        output.insert("sizes".to_string(), Value::Array(sizes.into_iter().map(Value::String).collect()));
+        output.insert("color_swatches".to_string(), color_swatches);
         output.insert("images".to_string(), Value::Array(images));
+        output.insert("image_variants".to_string(), Value::Array(image_variants));
+        output.insert("total_image_count".to_string(), Value::from(total_image_count));
         output.insert("content".to_string(), text_content);
+        output.insert("is_amp".to_string(), Value::Bool(is_amp));
+        // og:type ("product", "article", "website", ...) lowercased for easy matching.
+        let og_type = output
+            .get("structured_data")
+            .and_then(|sd| sd.pointer("/open_graph/og:type"))
+            .and_then(|v| v.as_str())
+            .map(|t| Value::String(t.trim().to_lowercase()))
+            .unwrap_or(Value::Null);
+        output.insert("og_type".to_string(), og_type);
+        // Page language: <html lang>, else og:locale.
+        let locale = document
+            .root_element()
+            .value()
+            .attr("lang")
+            .or_else(|| {
+                output
+                    .get("structured_data")
+                    .and_then(|sd| sd.pointer("/open_graph/og:locale"))
+                    .and_then(|v| v.as_str())
+            })
+            .and_then(normalize_locale)
+            .map(Value::String)
+            .unwrap_or(Value::Null);
+        output.insert("locale".to_string(), locale);
+        output.insert("alternate_urls".to_string(), Value::Object(alternate_urls));
+        output.insert(
+            "canonical_url".to_string(),
+            canonical_url.map(Value::String).unwrap_or(Value::Null),
+        );
 
         let mut output_value = Value::Object(output);
         let mut output_str = serde_json::to_string(&output_value).unwrap_or_default();
@@ -78,8 +259,29 @@ impl ProductDataExtractor {
         output_value
     }
 
+    /// Approximates model tokenization: each punctuation/symbol character and
+    /// each non-ASCII letter (CJK, etc.) is a token, and ASCII alphanumeric runs
+    /// cost one token per 4 characters, so URLs and JSON punctuation aren't
+    /// undercounted the way a flat chars/4 ratio does. A token_char_ratio
+    /// switches back to a fixed ratio.
     fn estimate_tokens(&self, text: &str) -> usize {
-        text.len() / self.token_char_ratio
+        if let Some(ratio) = self.token_char_ratio {
+            return (text.chars().count() as f64 / ratio).ceil() as usize;
+        }
+        let mut tokens = 0;
+        let mut run: usize = 0;
+        for c in text.chars() {
+            if c.is_ascii_alphanumeric() {
+                run += 1;
+                continue;
+            }
+            tokens += run.div_ceil(4);
+            run = 0;
+            if !c.is_whitespace() {
+                tokens += 1;
+            }
+        }
+        tokens + run.div_ceil(4)
     }
 
     fn extract_structured_data(&self, document: &Html) -> Value {
@@ -94,23 +296,15 @@ impl ProductDataExtractor {
             if text.trim().is_empty() {
                 continue;
             }
-            if let Ok(data) = serde_json::from_str::<Value>(&text) {
-                if let Some(obj) = data.as_object() {
-                    if let Some(t) = obj.get("@type").and_then(|v| v.as_str()) {
-                        if matches!(t, "Product" | "Offer" | "AggregateOffer" | "ProductGroup") {
-                            json_ld_arr.push(Value::Object(obj.clone()));
-                        }
-                    } else if let Some(graph) = obj.get("@graph").and_then(|v| v.as_array()) {
-                        for item in graph {
-                            if let Some(item_obj) = item.as_object() {
-                                if let Some(t) = item_obj.get("@type").and_then(|v| v.as_str()) {
-                                    if matches!(t, "Product" | "Offer" | "ProductGroup") {
-                                        json_ld_arr.push(Value::Object(item_obj.clone()));
-                                    }
-                                }
-                            }
+            if let Some(data) = parse_json_ld(&text) {
+                // Shopify and others wrap several objects in one top-level array.
+                match &data {
+                    Value::Array(items) => {
+                        for item in items {
+                            collect_json_ld_products(item, &mut json_ld_arr);
                         }
                     }
+                    _ => collect_json_ld_products(&data, &mut json_ld_arr),
                 }
             }
         }
@@ -140,9 +334,85 @@ impl ProductDataExtractor {
             "meta_tags": meta_tags,
             "open_graph": open_graph,
             "twitter_card": twitter_card,
+            "microdata": self.extract_microdata(document).unwrap_or(Value::Null),
+            "rdfa": self.extract_rdfa(document),
         })
     }
 
+    /// RDFa product annotations (`property="product:price:amount"`,
+    /// `og:product:...`) on any element, keyed by the property verbatim.
+    /// Repeated properties keep the first value.
+    fn extract_rdfa(&self, document: &Html) -> Value {
+        let sel = Selector::parse("[property^=\"product:\"], [property^=\"og:product:\"]").unwrap();
+        let mut rdfa = Map::new();
+        for el in document.select(&sel) {
+            let Some(property) = el.value().attr("property").map(str::trim) else {
+                continue;
+            };
+            if let Some(value) = microdata_value(el) {
+                rdfa.entry(property.to_string()).or_insert(Value::String(value));
+            }
+        }
+        Value::Object(rdfa)
+    }
+
+    /// schema.org microdata for the first `itemscope` whose `itemtype` mentions
+    /// Product, as `{itemprop: value}`. Nested scopes (offers, brand) become
+    /// nested objects; repeated props keep the first value.
+    fn extract_microdata(&self, document: &Html) -> Option<Value> {
+        let sel = Selector::parse("[itemscope][itemtype*=\"Product\"]").unwrap();
+        let scope = document.select(&sel).next()?;
+        let props = microdata_scope(scope);
+        (!props.is_empty()).then_some(Value::Object(props))
+    }
+
+    fn is_amp_page(&self, document: &Html) -> bool {
+        let root = document.root_element().value();
+        root.attr("amp").is_some() || root.attr("⚡").is_some()
+    }
+
+    fn extract_canonical_url(&self, document: &Html, base_url: &str) -> Option<String> {
+        let sel = Selector::parse("link[rel='canonical']").ok()?;
+        let href = document
+            .select(&sel)
+            .filter_map(|link| link.value().attr("href"))
+            .map(str::trim)
+            .find(|href| !href.is_empty())?;
+        match Url::parse(base_url).and_then(|base| base.join(href)) {
+            Ok(full) => Some(full.to_string()),
+            Err(_) => Some(href.to_string()),
+        }
+    }
+
+    /// `<link rel="alternate" hreflang>` regional versions as `{lang: absolute url}`;
+    /// `x-default` is kept as is.
+    fn extract_alternate_urls(&self, document: &Html, base_url: &str) -> Map<String, Value> {
+        let sel = Selector::parse("link[rel='alternate'][hreflang]").unwrap();
+        let base = Url::parse(base_url).ok();
+        let mut alternates = Map::new();
+        for link in document.select(&sel) {
+            let (Some(lang), Some(href)) = (link.value().attr("hreflang"), link.value().attr("href")) else {
+                continue;
+            };
+            let href = href.trim();
+            let Some(lang) = normalize_locale(lang).or_else(|| {
+                lang.trim().eq_ignore_ascii_case("x-default").then(|| "x-default".to_string())
+            }) else {
+                continue;
+            };
+            if href.is_empty() {
+                continue;
+            }
+            let full = base
+                .as_ref()
+                .and_then(|base| base.join(href).ok())
+                .map(|u| u.to_string())
+                .unwrap_or_else(|| href.to_string());
+            alternates.entry(lang).or_insert(Value::String(full));
+        }
+        alternates
+    }
+
     fn extract_inline_json(&self, document: &Html) -> Vec<String> {
         let mut images = Vec::new();
         let script_sel = Selector::parse("script").unwrap();
@@ -152,6 +422,8 @@ impl ProductDataExtractor {
             "window.__NEXT_DATA__",
             "window.__PRODUCT_DATA__",
             "__INITIAL_STATE__",
+            "__APOLLO_STATE__",
+            "__RELAY_STORE__",
         ];
 
         let patterns = [
@@ -163,7 +435,9 @@ impl ProductDataExtractor {
             .iter()
             .map(|p| Regex::new(p).unwrap())
             .collect();
-        let url_re = Regex::new(r#"https?://[^"']+\.(?:jpg|jpeg|png|webp)"#).unwrap();
+        // CDN urls often carry a cache-busting or sizing query (`.jpg?v=2`).
+        let url_re =
+            Regex::new(r#"https?://[^"']+\.(?:jpg|jpeg|png|webp|avif|gif)\b(?:\?[^"'\s]*)?"#).unwrap();
 
         for script in document.select(&script_sel) {
             let value = script.value();
@@ -183,11 +457,7 @@ impl ProductDataExtractor {
             for re_pat in &regexes {
                 for caps in re_pat.captures_iter(&script_content) {
                     if let Some(m) = caps.get(1) {
-                        let mut array_content = m.as_str().to_string();
-                        // best-effort unicode escape handling
-                        if let Ok(decoded) = serde_json::from_str::<String>(&format!("\"{}\"", array_content)) {
-                            array_content = decoded;
-                        }
+                        let array_content = unescape_json_fragment(m.as_str());
                         for m in url_re.find_iter(&array_content) {
                             let mut url = m.as_str().to_string();
                             if url.contains("__IMAGE_PARAMS__") {
@@ -203,6 +473,98 @@ impl ProductDataExtractor {
         images
     }
 
+    /// Product fields from an embedded Apollo/Relay client cache
+    /// (`window.__APOLLO_STATE__ = {...}`): {name, price, currency, images}.
+    fn extract_app_state(&self, document: &Html) -> Option<Value> {
+        let script_sel = Selector::parse("script").unwrap();
+        let markers = ["__APOLLO_STATE__", "__RELAY_STORE__"];
+
+        let state = document.select(&script_sel).find_map(|script| {
+            let text = script.text().collect::<String>();
+            let marker_end = markers
+                .iter()
+                .find_map(|m| text.find(m).map(|i| i + m.len()))?;
+            let assign = text[marker_end..].find('=')?;
+            parse_json_ld(&text[marker_end + assign + 1..]).filter(|v| v.is_object())
+        })?;
+
+        // Prefer an entry typed as a product; otherwise the first one that has
+        // both a name and a price.
+        let entries: Vec<&Value> = state
+            .as_object()?
+            .values()
+            .flat_map(|v| match v {
+                Value::Object(map) if map.contains_key("__typename") => vec![v],
+                // Relay stores nest records one level down.
+                Value::Object(map) => map.values().filter(|r| r.is_object()).collect(),
+                _ => Vec::new(),
+            })
+            .collect();
+        let is_product = |entry: &&Value| {
+            entry
+                .get("__typename")
+                .and_then(|t| t.as_str())
+                .is_some_and(|t| t.to_lowercase().contains("product"))
+        };
+        let product = entries.iter().copied().find(is_product).or_else(|| {
+            entries
+                .iter()
+                .copied()
+                .find(|e| e.get("name").is_some() && app_state_price(e, &state, 0).is_some())
+        })?;
+
+        let name = product
+            .get("name")
+            .or_else(|| product.get("title"))
+            .and_then(|v| v.as_str())
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty());
+        let (price, currency) = app_state_price(product, &state, 0).unwrap_or((Value::Null, None));
+        let mut images = Vec::new();
+        collect_app_state_images(product, &state, 0, &mut images);
+
+        if name.is_none() && price.is_null() && images.is_empty() {
+            return None;
+        }
+        Some(json!({
+            "name": name,
+            "price": price,
+            "currency": currency,
+            "images": images,
+        }))
+    }
+
+    /// Product from a Google Tag Manager ecommerce push (`dataLayer.push({...})`
+    /// or `dataLayer = [{...}]`): {name, brand, price, currency, category, id}.
+    /// Reads Universal Analytics `ecommerce.detail.products[0]` and GA4
+    /// `ecommerce.items[0]`; pushes that aren't valid JSON are skipped.
+    fn extract_data_layer(&self, document: &Html) -> Option<Value> {
+        let script_sel = Selector::parse("script").unwrap();
+        let markers = ["dataLayer.push(", "dataLayer ="];
+
+        for script in document.select(&script_sel) {
+            let text = script.text().collect::<String>();
+            if !text.contains("dataLayer") || !text.contains("ecommerce") {
+                continue;
+            }
+            for marker in markers {
+                for (start, _) in text.match_indices(marker) {
+                    let Some(value) = parse_json_ld(&text[start + marker.len()..]) else {
+                        continue;
+                    };
+                    let pushes = match value {
+                        Value::Array(items) => items,
+                        other => vec![other],
+                    };
+                    if let Some(product) = pushes.iter().find_map(data_layer_product) {
+                        return Some(product);
+                    }
+                }
+            }
+        }
+        None
+    }
+
     fn flatten_json_ld_images(&self, structured_data: &Value) -> Vec<String> {
         let mut images = Vec::new();
         let json_ld_arr = structured_data
@@ -251,10 +613,36 @@ impl ProductDataExtractor {
         images
     }
 
+    /// `(url, width)` for every candidate in `<img>`/`<source>` srcset (and
+    /// data-srcset) attributes; width comes from a `1200w` descriptor.
+    fn extract_srcset_candidates(&self, document: &Html, base_url: &str) -> Vec<(String, Option<u32>)> {
+        let sel = Selector::parse("img[srcset], img[data-srcset], source[srcset]").unwrap();
+        let Ok(base) = Url::parse(base_url) else {
+            return Vec::new();
+        };
+        let mut candidates = Vec::new();
+        for el in document.select(&sel).take(self.max_img_scan) {
+            let value = el.value();
+            let srcset = value.attr("srcset").or_else(|| value.attr("data-srcset")).unwrap_or("");
+            for candidate in srcset.split(',') {
+                let mut parts = candidate.split_whitespace();
+                let Some(Ok(full)) = parts.next().map(|src| base.join(src)) else {
+                    continue;
+                };
+                let width = parts
+                    .next()
+                    .and_then(|d| d.strip_suffix('w'))
+                    .and_then(|w| w.parse().ok());
+                candidates.push((full.to_string(), width));
+            }
+        }
+        candidates
+    }
+
     fn extract_preload_images(&self, document: &Html, base_url: &str) -> Vec<String> {
         let mut images = Vec::new();
         let link_sel = Selector::parse("link[rel='preload']").unwrap();
-        let img_ext_re = Regex::new(r"\.(jpg|jpeg|png|webp)$").unwrap();
+        let img_ext_re = Regex::new(r"\.(jpg|jpeg|png|webp|avif|gif)$").unwrap();
 
         for link in document.select(&link_sel) {
             let value = link.value();
@@ -263,9 +651,9 @@ impl ProductDataExtractor {
             if as_attr == "image" && !href.is_empty() {
                 if let Ok(base) = Url::parse(base_url) {
                     if let Ok(full) = base.join(href) {
-                        let full_url = full.to_string();
-                        if img_ext_re.is_match(&full_url.to_lowercase()) {
-                            images.push(full_url);
+                        // Match on the path so `image.jpg?v=2` still counts.
+                        if img_ext_re.is_match(&full.path().to_lowercase()) {
+                            images.push(full.to_string());
                         }
                     }
                 }
@@ -310,6 +698,54 @@ impl ProductDataExtractor {
             }
         }
 
+        // Bare amounts ("49.99") whose symbol lives in a sibling/child element:
+        // <span class="currency">$</span><span class="amount">49.99</span>
+        let bare_amount_re = Regex::new(r"^\d[\d,.'\s]*$").unwrap();
+        let currency_sel = Selector::parse("[class*=\"currency\"], [itemprop=\"priceCurrency\"]").unwrap();
+        let is_currency_el = |el: &ElementRef| {
+            el.value().attr("itemprop") == Some("priceCurrency")
+                || el
+                    .value()
+                    .attr("class")
+                    .is_some_and(|c| c.to_lowercase().contains("currency"))
+        };
+        let currency_text = |el: ElementRef| {
+            el.value()
+                .attr("content")
+                .map(|c| c.trim().to_string())
+                .filter(|c| !c.is_empty())
+                .or_else(|| {
+                    let text = el.text().collect::<String>().trim().to_string();
+                    (!text.is_empty() && text.chars().count() <= 4).then_some(text)
+                })
+        };
+        let mut recovered = Vec::new();
+        if let Ok(amount_sel) = Selector::parse("[class*=\"price\"], [class*=\"amount\"], [itemprop=\"price\"]") {
+            for elem in document.select(&amount_sel).take(50) {
+                let text = elem.text().collect::<String>().trim().to_string();
+                if text.is_empty() || !bare_amount_re.is_match(&text) {
+                    continue;
+                }
+                let currency = elem
+                    .select(&currency_sel)
+                    .next()
+                    .or_else(|| elem.prev_siblings().filter_map(ElementRef::wrap).find(is_currency_el))
+                    .or_else(|| elem.next_siblings().filter_map(ElementRef::wrap).find(is_currency_el))
+                    .and_then(currency_text);
+                let Some(currency) = currency else {
+                    continue;
+                };
+                let combined = if currency.chars().all(|c| c.is_ascii_alphabetic()) {
+                    format!("{} {}", text, currency.to_uppercase())
+                } else {
+                    format!("{}{}", currency, text)
+                };
+                recovered.push(whitespace_re.replace_all(&combined, " ").to_string());
+            }
+        }
+        // Ahead of the text signals so the 10-signal cap never drops them.
+        price_signals.splice(0..0, recovered);
+
         let mut seen = std::collections::HashSet::new();
         let mut unique = Vec::new();
         for price in price_signals {
@@ -324,6 +760,353 @@ impl ProductDataExtractor {
         unique
     }
 
+    /// E-commerce platform from well-known markup markers; "custom" when none match.
+    fn detect_platform(&self, document: &Html, html: &str) -> &'static str {
+        let body_class = Selector::parse("body")
+            .ok()
+            .and_then(|sel| document.select(&sel).next())
+            .and_then(|body| body.value().attr("class"))
+            .unwrap_or("")
+            .to_lowercase();
+
+        if html.contains("cdn.shopify.com") || html.contains("Shopify.theme") || html.contains("window.Shopify") {
+            "shopify"
+        } else if body_class.contains("woocommerce") || html.contains("wp-content/plugins/woocommerce") {
+            "woocommerce"
+        } else if html.contains("Magento_") || html.contains("Mage.Cookies") || html.contains("mage/cookies") {
+            "magento"
+        } else if html.contains("cdn11.bigcommerce.com") || html.contains("BCData") {
+            "bigcommerce"
+        } else {
+            "custom"
+        }
+    }
+
+    /// Machine-readable microdata price: the `content` attribute of
+    /// `[itemprop=price]` / `[itemprop=lowPrice]` (text as a fallback), paired with
+    /// `[itemprop=priceCurrency]`. Returns `{"price": "49.99", "currency": "USD"}`.
+    fn extract_microdata_price(&self, document: &Html) -> Option<Value> {
+        let price_sel = Selector::parse("[itemprop=\"price\"], [itemprop=\"lowPrice\"]").unwrap();
+        let currency_sel = Selector::parse("[itemprop=\"priceCurrency\"]").unwrap();
+        let attr_or_text = |el: ElementRef| {
+            el.value()
+                .attr("content")
+                .map(|c| c.trim().to_string())
+                .filter(|c| !c.is_empty())
+                .or_else(|| {
+                    let text = el.text().collect::<String>().trim().to_string();
+                    (!text.is_empty()).then_some(text)
+                })
+        };
+
+        // Prefer an element carrying `content`; fall back to the first with text.
+        let price = document
+            .select(&price_sel)
+            .find(|el| el.value().attr("content").is_some_and(|c| !c.trim().is_empty()))
+            .or_else(|| document.select(&price_sel).next())
+            .and_then(attr_or_text)?;
+        let currency = document.select(&currency_sel).next().and_then(attr_or_text);
+        Some(json!({ "price": price, "currency": currency }))
+    }
+
+    /// True when the price area says the price is hidden behind a login
+    /// ("Sign in to see price", "Members only price", ...).
+    fn detect_price_gate(&self, document: &Html) -> bool {
+        let gate_re = Regex::new(
+            r"(?i)\b(sign|log)\s*-?\s*in\s+(to\s+(see|view|unlock)|for)\s+(the\s+|our\s+)?price|members?[- ]only\s+pric|price\s+(available|shown)\s+(after|on|upon)\s+(log|sign)\s*-?\s*in",
+        )
+        .unwrap();
+        let Ok(sel) = Selector::parse("[class*=\"price\"], [id*=\"price\"], [data-price], [itemprop=\"price\"]") else {
+            return false;
+        };
+        document.select(&sel).take(50).any(|elem| {
+            let text = elem.text().collect::<Vec<_>>().join(" ");
+            text.len() < 200 && gate_re.is_match(&text)
+        })
+    }
+
+    /// Prices that sit next to a size/variant label (e.g. a size picker row
+    /// "M — $49.99"), as `{"variant": "M", "price": "$49.99"}` pairs.
+    fn extract_variant_prices(&self, document: &Html) -> Vec<Value> {
+        let price_regex =
+            Regex::new(r#"[\$£€¥₹]\s*[\d,]+\.?\d*|\d+[\.,]\d+\s*(?:USD|EUR|GBP|INR|CAD|AUD)"#)
+                .unwrap();
+        let label_regex = Regex::new(
+            r"(?i)^(?:size\s*)?(xxs|xs|s|m|l|xl|xxl|xxxl|[2-5]xl|one size|small|medium|large|\d{1,2}(?:\.5)?|\d+\s*(?:ml|oz|cm))$",
+        )
+        .unwrap();
+        let selectors = [
+            "[class*=\"variant\"]",
+            "[class*=\"size\"]",
+            "[class*=\"option\"]",
+            "option",
+            "li",
+        ];
+        let label_of = |text: &str, price: &str| {
+            let rest = text.replacen(price, "", 1);
+            let rest = rest.trim_matches(|c: char| c.is_whitespace() || ":-–—|/()".contains(c));
+            label_regex.is_match(rest).then(|| rest.to_string())
+        };
+
+        let mut seen = std::collections::HashSet::new();
+        let mut pairs = Vec::new();
+        for sel_str in &selectors {
+            let Ok(sel) = Selector::parse(sel_str) else {
+                continue;
+            };
+            for elem in document.select(&sel).take(200) {
+                let text = elem.text().collect::<Vec<_>>().join(" ");
+                let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                if text.is_empty() || text.len() > 60 {
+                    continue;
+                }
+                let Some(price) = price_regex.find(&text).map(|m| m.as_str().trim().to_string()) else {
+                    continue;
+                };
+                let Some(variant) = label_of(&text, &price) else {
+                    continue;
+                };
+                if seen.insert((variant.to_lowercase(), price.clone())) {
+                    pairs.push(json!({ "variant": variant, "price": price }));
+                    if pairs.len() >= 20 {
+                        return pairs;
+                    }
+                }
+            }
+        }
+        pairs
+    }
+
+    /// Semantic sale markup: `<del>` holds the old price and the `<ins>` next
+    /// to it (or the remaining sibling text) holds the current one.
+    fn extract_sale_markup(&self, document: &Html) -> Option<Value> {
+        let price_regex =
+            Regex::new(r#"[\$£€¥₹]\s*[\d,]+\.?\d*|\d+[\.,]\d+\s*(?:USD|EUR|GBP|INR|CAD|AUD)"#)
+                .unwrap();
+        let del_sel = Selector::parse("del").unwrap();
+        let ins_sel = Selector::parse("ins").unwrap();
+
+        for del in document.select(&del_sel).take(20) {
+            let del_text = del.text().collect::<String>();
+            let Some(original) = price_regex.find(&del_text) else {
+                continue;
+            };
+            let Some(parent) = del.parent().and_then(ElementRef::wrap) else {
+                continue;
+            };
+
+            let current = match parent.select(&ins_sel).next() {
+                Some(ins) => {
+                    let ins_text = ins.text().collect::<String>();
+                    price_regex.find(&ins_text).map(|m| m.as_str().trim().to_string())
+                }
+                None => {
+                    // No <ins>: take the first price in the siblings outside <del>.
+                    let sibling_text: String = parent
+                        .children()
+                        .filter(|child| {
+                            ElementRef::wrap(*child)
+                                .map(|el| el.value().name() != "del")
+                                .unwrap_or(true)
+                        })
+                        .flat_map(|child| {
+                            ElementRef::wrap(child)
+                                .map(|el| el.text().collect::<String>())
+                                .or_else(|| child.value().as_text().map(|t| t.to_string()))
+                        })
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    price_regex.find(&sibling_text).map(|m| m.as_str().trim().to_string())
+                }
+            };
+
+            if let Some(current) = current {
+                return Some(json!({
+                    "price": current,
+                    "original_price": original.as_str().trim(),
+                }));
+            }
+        }
+        None
+    }
+
+    /// Options of a size `<select>` (its own or its parent's class/id/name mentions
+    /// "size"), skipping placeholders. Deduplicated case-insensitively, in page order.
+    fn extract_sizes(&self, document: &Html) -> Vec<String> {
+        let select_sel = Selector::parse("select").unwrap();
+        let option_sel = Selector::parse("option").unwrap();
+        let mentions_size = |el: ElementRef| {
+            let v = el.value();
+            [v.attr("class"), v.attr("id"), v.attr("name")]
+                .iter()
+                .flatten()
+                .any(|a| a.to_lowercase().contains("size"))
+        };
+
+        let mut seen = std::collections::HashSet::new();
+        let mut sizes = Vec::new();
+        for select in document.select(&select_sel) {
+            let parent_mentions_size = select
+                .parent()
+                .and_then(ElementRef::wrap)
+                .map(mentions_size)
+                .unwrap_or(false);
+            if !mentions_size(select) && !parent_mentions_size {
+                continue;
+            }
+            for option in select.select(&option_sel) {
+                let text = option.text().collect::<Vec<_>>().join(" ");
+                let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                let lower = text.to_lowercase();
+                if text.is_empty()
+                    || option.value().attr("value") == Some("")
+                    || lower.starts_with("select")
+                    || lower.starts_with("choose")
+                    || text.len() > 40
+                {
+                    continue;
+                }
+                if seen.insert(lower) {
+                    sizes.push(text);
+                }
+            }
+        }
+        sizes
+    }
+
+    /// Color swatches (`[class*=swatch]` elements that aren't about size):
+    /// `{"colors": [...], "selected": "Navy" | null}`. Labels come from
+    /// aria-label/title/data attributes, an inner `<img alt>`, or short text;
+    /// the selected swatch has a selected/active class or aria-checked/
+    /// aria-pressed/aria-selected="true".
+    fn extract_color_swatches(&self, document: &Html) -> Value {
+        let swatch_sel = Selector::parse("[class*=\"swatch\"]").unwrap();
+        let img_sel = Selector::parse("img[alt]").unwrap();
+        let mut seen = std::collections::HashSet::new();
+        let mut colors = Vec::new();
+        let mut selected: Option<String> = None;
+
+        for el in document.select(&swatch_sel).take(self.max_img_scan) {
+            let value = el.value();
+            if matches!(value.name(), "script" | "style" | "select" | "option") {
+                continue;
+            }
+            let about_size = value
+                .attrs()
+                .any(|(name, v)| name != "style" && (name.contains("size") || v.to_lowercase().contains("size")));
+            // Wrappers like `.swatches` hold the real swatches.
+            let is_container = el.select(&swatch_sel).filter(|inner| inner.id() != el.id()).nth(1).is_some();
+            if about_size || is_container {
+                continue;
+            }
+            let label = ["aria-label", "title", "data-value", "data-color", "data-option-value"]
+                .iter()
+                .find_map(|attr| value.attr(attr))
+                .map(str::to_string)
+                .or_else(|| el.select(&img_sel).next().and_then(|img| img.value().attr("alt")).map(str::to_string))
+                .unwrap_or_else(|| el.text().collect::<Vec<_>>().join(" "));
+            let label = label.split_whitespace().collect::<Vec<_>>().join(" ");
+            if label.is_empty() || label.len() > 40 {
+                continue;
+            }
+
+            let class = value.attr("class").unwrap_or("").to_lowercase();
+            let is_selected = class.contains("selected")
+                || class.contains("active")
+                || ["aria-checked", "aria-pressed", "aria-selected"]
+                    .iter()
+                    .any(|attr| value.attr(attr) == Some("true"));
+            if is_selected && selected.is_none() {
+                selected = Some(label.clone());
+            }
+            if seen.insert(label.to_lowercase()) {
+                colors.push(Value::String(label));
+            }
+        }
+        json!({ "colors": colors, "selected": selected })
+    }
+
+    /// Shipping cost / delivery estimate text from `[class*=shipping]` or
+    /// `[class*=delivery]` blocks, capped at SHIPPING_INFO_MAX_CHARS.
+    fn extract_shipping_info(&self, document: &Html) -> Option<String> {
+        let sel = Selector::parse("[class*=\"shipping\"], [class*=\"delivery\"]").ok()?;
+        let hint_re = Regex::new(r"(?i)ship|deliver|dispatch|free|days?\b|returns?").unwrap();
+
+        document.select(&sel).take(20).find_map(|el| {
+            if matches!(el.value().name(), "script" | "style" | "noscript" | "input" | "select") {
+                return None;
+            }
+            let text = el.text().collect::<Vec<_>>().join(" ");
+            let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+            if text.len() < 5 || !hint_re.is_match(&text) {
+                return None;
+            }
+            Some(text.chars().take(SHIPPING_INFO_MAX_CHARS).collect())
+        })
+    }
+
+    /// First `<table>` that sits under a size-guide heading (caption, a preceding
+    /// heading, or an ancestor's class/id) and whose header row looks like sizes or
+    /// body measurements, so spec tables are left alone.
+    /// Returns `{"headers": [...], "rows": [[...], ...]}`.
+    fn extract_size_chart(&self, document: &Html) -> Option<Value> {
+        let context_re = Regex::new(r"(?i)size|fit\b|measurement").unwrap();
+        let header_re = Regex::new(
+            r"(?i)\b(size|xxs|xs|s|m|l|xl|xxl|chest|bust|waist|hips?|inseam|length|shoulder|sleeve|cm|in|inches|eu|us|uk|it|fr)\b",
+        )
+        .unwrap();
+        let table_sel = Selector::parse("table").unwrap();
+        let caption_sel = Selector::parse("caption").unwrap();
+        let heading_sel = Selector::parse("h1, h2, h3, h4, h5, h6").unwrap();
+        let row_sel = Selector::parse("tr").unwrap();
+        let cell_sel = Selector::parse("th, td").unwrap();
+        let clean = |el: ElementRef| {
+            let text = el.text().collect::<Vec<_>>().join(" ");
+            text.split_whitespace().collect::<Vec<_>>().join(" ")
+        };
+
+        for table in document.select(&table_sel).take(20) {
+            let mut context = table.select(&caption_sel).map(clean).collect::<Vec<_>>();
+            context.extend(
+                table
+                    .prev_siblings()
+                    .filter_map(ElementRef::wrap)
+                    .take(3)
+                    .map(clean),
+            );
+            for ancestor in table.ancestors().filter_map(ElementRef::wrap).take(4) {
+                let attrs = ancestor.value();
+                context.push(format!("{} {}", attrs.attr("class").unwrap_or(""), attrs.id().unwrap_or("")));
+                if let Some(heading) = ancestor.select(&heading_sel).next() {
+                    context.push(clean(heading));
+                }
+            }
+            if !context.iter().any(|text| context_re.is_match(text)) {
+                continue;
+            }
+
+            let mut rows = table
+                .select(&row_sel)
+                .map(|row| row.select(&cell_sel).map(clean).collect::<Vec<_>>());
+            let Some(headers) = rows.next() else {
+                continue;
+            };
+            let matching_headers = headers.iter().filter(|h| header_re.is_match(h)).count();
+            if headers.len() < 2 || matching_headers * 2 < headers.len() {
+                continue;
+            }
+            let body: Vec<Vec<String>> = rows
+                .filter(|row| row.iter().any(|cell| !cell.is_empty()))
+                .take(30)
+                .collect();
+            if body.is_empty() {
+                continue;
+            }
+            return Some(json!({ "headers": headers, "rows": body }));
+        }
+        None
+    }
+
     fn extract_text_content(&self, document: &Html) -> Value {
         let mut title = String::new();
         let mut headings = Vec::new();
@@ -419,20 +1202,44 @@ impl ProductDataExtractor {
         })
     }
 
-    fn filter_product_images(&self, document: &Html, base_url: &str) -> Vec<ImageInfo> {
+    /// `name_hints` (lowercased product name/brand) in an image's alt text mark
+    /// it as a product shot rather than a decorative image.
+    fn filter_product_images(
+        &self,
+        document: &Html,
+        base_url: &str,
+        is_amp: bool,
+        name_hints: &[String],
+    ) -> Vec<ImageInfo> {
         let mut images = Vec::new();
+        // AMP candidates that missed the score threshold; used only if nothing else qualifies.
+        let mut amp_fallback = Vec::new();
 
-        let excluded_patterns = [
-            "logo", "icon", "favicon", "sprite", "loading", "placeholder",
-            "social", "facebook", "twitter", "instagram", "youtube",
-            "payment", "visa", "mastercard", "paypal", "stripe",
-            "shipping", "delivery", "banner", "advertisement",
-        ];
+        // Walk images: the gallery container first (if one is found), then the
+        // rest of the document, each up to max_img_scan.
+        let img_selector = if is_amp { "img, amp-img" } else { "img" };
+        if let Ok(img_sel) = Selector::parse(img_selector) {
+            let container = GALLERY_CONTAINER_SELECTORS.iter().find_map(|sel_str| {
+                let sel = Selector::parse(sel_str).ok()?;
+                document
+                    .select(&sel)
+                    .find(|el| el.select(&img_sel).next().is_some())
+            });
+            let mut candidates: Vec<ElementRef> = Vec::new();
+            if let Some(container) = container {
+                candidates.extend(container.select(&img_sel).take(self.max_img_scan));
+            }
+            let in_container: std::collections::HashSet<_> = candidates.iter().map(|el| el.id()).collect();
+            candidates.extend(
+                document
+                    .select(&img_sel)
+                    .filter(|el| !in_container.contains(&el.id()))
+                    .take(self.max_img_scan),
+            );
 
-        // Walk images
-        if let Ok(img_sel) = Selector::parse("img") {
-            for img in document.select(&img_sel).take(50) {
+            for img in candidates {
                 let value = img.value();
+                let is_amp_img = value.name() == "amp-img";
                 let mut src = value
                     .attr("src")
                     .or_else(|| value.attr("data-src"))
@@ -452,12 +1259,17 @@ impl ProductDataExtractor {
                 }
 
                 let src_lower = src.to_lowercase();
-                if excluded_patterns.iter().any(|p| src_lower.contains(p)) {
+                if self.excluded_image_patterns.iter().any(|p| src_lower.contains(p.as_str())) {
                     continue;
                 }
 
-                // rough size check
-                if let (Some(w), Some(h)) = (value.attr("width"), value.attr("height")) {
+                // rough size check (responsive AMP layouts use width/height as an aspect ratio)
+                let amp_ratio_layout = is_amp_img
+                    && matches!(
+                        value.attr("layout"),
+                        Some("responsive" | "fill" | "flex-item" | "intrinsic")
+                    );
+                if let (false, Some(w), Some(h)) = (amp_ratio_layout, value.attr("width"), value.attr("height")) {
                     if let (Ok(w), Ok(h)) = (w.replace("px", "").parse::<i32>(), h.replace("px", "").parse::<i32>()) {
                         if w < 100 || h < 100 {
                             continue;
@@ -475,6 +1287,10 @@ impl ProductDataExtractor {
                 if !alt.is_empty() && alt.len() > 10 {
                     score += 2;
                 }
+                let alt_lower = alt.to_lowercase();
+                if name_hints.iter().any(|hint| alt_lower.contains(hint.as_str())) {
+                    score += 3;
+                }
                 if ["cdn", "media", "assets", "images"].iter().any(|p| src_lower.contains(p)) {
                     score += 1;
                 }
@@ -501,11 +1317,23 @@ impl ProductDataExtractor {
 
                 if score >= 2 {
                     images.push(ImageInfo { src, alt, score });
+                } else if is_amp_img {
+                    amp_fallback.push(ImageInfo { src, alt, score });
                 }
             }
         }
 
-        images.sort_by(|a, b| b.score.cmp(&a.score));
+        // Pure AMP pages rarely carry the class hints we score on; don't return
+        // zero images just because the page is AMP.
+        if images.is_empty() {
+            images = amp_fallback;
+        }
+
+        // Page order (gallery container first) follows the site's own gallery
+        // sequence, so the front shot stays first.
+        if !self.preserve_dom_order {
+            images.sort_by(|a, b| b.score.cmp(&a.score));
+        }
         images.truncate(15);
         images
     }
@@ -541,8 +1369,754 @@ impl ProductDataExtractor {
     }
 }
 
+/// Collects the itemprops under `scope`, without descending into nested scopes
+/// except as the value of their own itemprop.
+fn microdata_scope(scope: ElementRef) -> Map<String, Value> {
+    fn walk(parent: ElementRef, props: &mut Map<String, Value>) {
+        for child in parent.children().filter_map(ElementRef::wrap) {
+            let el = child.value();
+            let nested = el.attr("itemscope").is_some();
+            if let Some(name) = el.attr("itemprop").map(str::trim).filter(|n| !n.is_empty()) {
+                let value = if nested {
+                    Some(Value::Object(microdata_scope(child)))
+                } else {
+                    microdata_value(child).map(Value::String)
+                };
+                if let Some(value) = value {
+                    props.entry(name.to_string()).or_insert(value);
+                }
+            }
+            if !nested {
+                walk(child, props);
+            }
+        }
+    }
+
+    let mut props = Map::new();
+    walk(scope, &mut props);
+    props
+}
+
+/// `content` attribute first (meta tags, machine-readable prices), then text.
+/// Shared by microdata and RDFa, which resolve values the same way.
+fn microdata_value(el: ElementRef) -> Option<String> {
+    let value = match el.value().attr("content") {
+        Some(content) => content.trim().to_string(),
+        None => el.text().collect::<Vec<_>>().join(" ").split_whitespace().collect::<Vec<_>>().join(" "),
+    };
+    (!value.is_empty()).then(|| value.chars().take(MICRODATA_VALUE_MAX_CHARS).collect())
+}
+
+/// "en_US" / "EN-us" → "en-us"; None unless it looks like a language tag
+/// (2-3 letter language, optional 2-4 character region/script).
+pub(crate) fn normalize_locale(raw: &str) -> Option<String> {
+    let locale = raw.trim().replace('_', "-").to_ascii_lowercase();
+    let mut parts = locale.split('-');
+    let lang = parts.next()?;
+    if !(2..=3).contains(&lang.len()) || !lang.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    match (parts.next(), parts.next()) {
+        (None, _) => Some(locale),
+        (Some(region), None) if (2..=4).contains(&region.len()) && region.chars().all(|c| c.is_ascii_alphanumeric()) => {
+            Some(locale)
+        }
+        _ => None,
+    }
+}
+
+/// Defaults plus IMAGE_EXCLUDE_PATTERNS, minus IMAGE_EXCLUDE_REMOVE (both comma-separated).
+fn excluded_image_patterns_from_env() -> Vec<String> {
+    let list = |name: &str| -> Vec<String> {
+        std::env::var(name)
+            .unwrap_or_default()
+            .split(',')
+            .map(|p| p.trim().to_lowercase())
+            .filter(|p| !p.is_empty())
+            .collect()
+    };
+    let removed = list("IMAGE_EXCLUDE_REMOVE");
+    let mut patterns: Vec<String> = DEFAULT_EXCLUDED_IMAGE_PATTERNS
+        .iter()
+        .map(|p| p.to_string())
+        .collect();
+    for extra in list("IMAGE_EXCLUDE_PATTERNS") {
+        if !patterns.contains(&extra) {
+            patterns.push(extra);
+        }
+    }
+    patterns.retain(|p| !removed.contains(p));
+    patterns
+}
+
+const APP_STATE_MAX_DEPTH: usize = 6;
+
+/// {name, brand, price, currency, category, id} from one dataLayer push's
+/// `ecommerce` object, or None when it names no product.
+fn data_layer_product(push: &Value) -> Option<Value> {
+    let ecommerce = push.get("ecommerce")?;
+    let product = ecommerce
+        .pointer("/detail/products/0")
+        .or_else(|| ecommerce.pointer("/items/0"))?;
+    let field = |keys: &[&str]| {
+        keys.iter().find_map(|key| match product.get(*key)? {
+            Value::String(s) if !s.trim().is_empty() => Some(Value::String(s.trim().to_string())),
+            Value::Number(n) => Some(Value::Number(n.clone())),
+            _ => None,
+        })
+    };
+    let name = field(&["name", "item_name"]);
+    let price = field(&["price"]);
+    if name.is_none() && price.is_none() {
+        return None;
+    }
+    let currency = ecommerce
+        .get("currencyCode")
+        .or_else(|| ecommerce.get("currency"))
+        .or_else(|| product.get("currency"))
+        .and_then(|v| v.as_str())
+        .map(|s| s.trim().to_string());
+    Some(json!({
+        "name": name,
+        "brand": field(&["brand", "item_brand"]),
+        "price": price,
+        "currency": currency,
+        "category": field(&["category", "item_category"]),
+        "id": field(&["id", "item_id", "sku"]),
+    }))
+}
+
+/// Follows an Apollo `{"__ref": "Type:id"}` pointer into the normalized cache.
+fn resolve_app_state_ref<'a>(value: &'a Value, state: &'a Value) -> &'a Value {
+    value
+        .get("__ref")
+        .and_then(|r| r.as_str())
+        .and_then(|r| state.get(r))
+        .unwrap_or(value)
+}
+
+/// (amount, currency) from the usual GraphQL price shapes: a bare number or
+/// string, `{amount, currencyCode}`, or Shopify-style `priceRange.minVariantPrice`.
+fn app_state_price(entry: &Value, state: &Value, depth: usize) -> Option<(Value, Option<String>)> {
+    if depth > APP_STATE_MAX_DEPTH {
+        return None;
+    }
+    let entry = resolve_app_state_ref(entry, state);
+    let currency_of = |v: &Value| {
+        ["currencyCode", "currency", "priceCurrency"]
+            .iter()
+            .find_map(|k| v.get(*k).and_then(|c| c.as_str()))
+            .map(str::to_string)
+    };
+    for key in ["price", "salePrice", "currentPrice", "priceRange", "minVariantPrice", "amount", "value"] {
+        let Some(candidate) = entry.get(key) else { continue };
+        let candidate = resolve_app_state_ref(candidate, state);
+        match candidate {
+            Value::Number(_) => return Some((candidate.clone(), currency_of(entry))),
+            Value::String(s) if s.chars().any(|c| c.is_ascii_digit()) => {
+                return Some((candidate.clone(), currency_of(entry)));
+            }
+            Value::Object(_) => {
+                if let Some((amount, currency)) = app_state_price(candidate, state, depth + 1) {
+                    return Some((amount, currency.or_else(|| currency_of(candidate)).or_else(|| currency_of(entry))));
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Image URLs anywhere under a cache entry, following `__ref` pointers.
+fn collect_app_state_images(value: &Value, state: &Value, depth: usize, images: &mut Vec<String>) {
+    if depth > APP_STATE_MAX_DEPTH {
+        return;
+    }
+    match resolve_app_state_ref(value, state) {
+        Value::String(s) => {
+            let lower = s.to_lowercase();
+            let path = lower.split('?').next().unwrap_or("");
+            if lower.starts_with("http")
+                && [".jpg", ".jpeg", ".png", ".webp", ".gif", ".avif"].iter().any(|ext| path.ends_with(ext))
+                && !images.contains(s)
+            {
+                images.push(s.clone());
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                collect_app_state_images(item, state, depth + 1, images);
+            }
+        }
+        Value::Object(map) => {
+            for item in map.values() {
+                collect_app_state_images(item, state, depth + 1, images);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Escapes `</script` appearing inside JS string literals, regex literals or
+/// comments (`var x = "</script>";`), which otherwise closes the element early
+/// and drops the rest of the page from the parse tree. Everything else is left
+/// untouched.
+///
+/// This deliberately departs from the HTML tokenizer: browsers end a script at
+/// the first `</script` whatever the JS state, leaving the script's tail
+/// (`";...`) as page text and its JSON/state cut in half. For extraction the
+/// author's intent matters more than byte-exact browser behaviour, so a tag
+/// that only makes sense inside a string stays in the script. The JS tracking
+/// is a heuristic; when it never sees a closing tag in plain code (e.g. it
+/// misread some syntax and thinks a string is still open), the script is left
+/// exactly as a browser would split it.
+fn sanitize_script_boundaries(html: &str) -> String {
+    let bytes = html.as_bytes();
+    let lower = html.to_ascii_lowercase();
+    let lower_bytes = lower.as_bytes();
+    let mut out = String::with_capacity(html.len());
+    let mut pos = 0;
+
+    while let Some(open) = lower[pos..].find("<script").map(|i| pos + i) {
+        let Some(body_start) = lower[open..].find('>').map(|i| open + i + 1) else {
+            break;
+        };
+        out.push_str(&html[pos..body_start]);
+        // Templates and other non-JS script types aren't tokenized as code.
+        let tag = &lower[open..body_start];
+        let is_code = !tag.contains("type=") || ["javascript", "json", "module"].iter().any(|t| tag.contains(t));
+
+        // Where the browser would end the element; the fallback.
+        let first_close = lower[body_start..].find("</script").map(|i| body_start + i);
+        let (end, escapes) = if is_code {
+            match script_end_in_code(bytes, lower_bytes, body_start) {
+                Some(found) => (Some(found.0), found.1),
+                None => (first_close, Vec::new()),
+            }
+        } else {
+            (first_close, Vec::new())
+        };
+
+        let mut chunk_start = body_start;
+        for at in escapes {
+            out.push_str(&html[chunk_start..at]);
+            out.push_str("<\\/");
+            chunk_start = at + 2;
+        }
+        match end {
+            Some(end) => {
+                // Copy through the closing tag so the next search starts after it.
+                let close = lower[end..].find('>').map(|i| end + i + 1).unwrap_or(bytes.len());
+                out.push_str(&html[chunk_start..close]);
+                pos = close;
+            }
+            None => {
+                out.push_str(&html[chunk_start..]);
+                return out;
+            }
+        }
+    }
+    out.push_str(&html[pos..]);
+    out
+}
+
+/// Walks a script body from `start` tracking string/regex/comment state.
+/// Returns the offset of the first `</script` in plain code plus the offsets
+/// of every `</script` inside a string, regex or comment before it; None when
+/// no closing tag is ever reached in plain code.
+fn script_end_in_code(bytes: &[u8], lower_bytes: &[u8], start: usize) -> Option<(usize, Vec<usize>)> {
+    let mut quote: Option<u8> = None;
+    let mut regex = false;
+    let mut regex_class = false;
+    let mut line_comment = false;
+    let mut block_comment = false;
+    let mut escapes = Vec::new();
+    let mut i = start;
+    while i < bytes.len() {
+        let b = bytes[i];
+        if lower_bytes[i..].starts_with(b"</script") {
+            if quote.is_none() && !regex && !line_comment && !block_comment {
+                return Some((i, escapes));
+            }
+            escapes.push(i);
+            i += 2;
+            continue;
+        }
+        if let Some(q) = quote {
+            if b == b'\\' {
+                i += 1;
+            } else if b == q || (b == b'\n' && q != b'`') {
+                quote = None;
+            }
+        } else if regex {
+            match b {
+                b'\\' => i += 1,
+                b'[' => regex_class = true,
+                b']' => regex_class = false,
+                b'/' if !regex_class => regex = false,
+                // Regex literals can't span lines.
+                b'\n' => {
+                    regex = false;
+                    regex_class = false;
+                }
+                _ => {}
+            }
+        } else if line_comment {
+            line_comment = b != b'\n';
+        } else if block_comment {
+            if bytes[i..].starts_with(b"*/") {
+                block_comment = false;
+                i += 1;
+            }
+        } else {
+            match b {
+                b'"' | b'\'' | b'`' => quote = Some(b),
+                b'/' if bytes.get(i + 1) == Some(&b'/') => line_comment = true,
+                b'/' if bytes.get(i + 1) == Some(&b'*') => block_comment = true,
+                b'/' if slash_starts_regex(&bytes[start..i]) => regex = true,
+                _ => {}
+            }
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Whether a `/` following `code` opens a regex literal rather than dividing:
+/// true after an operator, an opening bracket, the start of the script or a
+/// keyword such as `return`; false after an identifier, number, string or
+/// closing bracket.
+fn slash_starts_regex(code: &[u8]) -> bool {
+    const REGEX_KEYWORDS: &[&[u8]] = &[
+        b"return", b"typeof", b"instanceof", b"in", b"of", b"new", b"delete", b"void", b"throw", b"case",
+        b"do", b"else",
+    ];
+    let code = code.trim_ascii_end();
+    let Some(&last) = code.last() else {
+        return true;
+    };
+    let is_word = |c: u8| c.is_ascii_alphanumeric() || c == b'_' || c == b'$';
+    if is_word(last) {
+        let word_start = code.iter().rposition(|&c| !is_word(c)).map_or(0, |i| i + 1);
+        return REGEX_KEYWORDS.contains(&&code[word_start..]);
+    }
+    !matches!(last, b')' | b']' | b'}' | b'"' | b'\'' | b'`')
+}
+
+/// Identity of an image independent of its size: width/height query params
+/// and size suffixes before the extension (`_1200x1600`, `_800w`) removed.
+/// `cdn.com/x_1200x1600.jpg`, `cdn.com/x_600w.jpg` and `cdn.com/x.jpg?w=1200`
+/// all map to `cdn.com/x.jpg`. Bare numbers (`jacket_0002.jpg`, `IMG_1234.jpg`)
+/// usually number distinct shots, so they're kept.
+pub(crate) fn canonical_image_key(url: &str) -> String {
+    lazy_static::lazy_static! {
+        static ref SIZE_SUFFIX_RE: Regex =
+            Regex::new(r"(?i)(?:_\d{2,5}x\d{2,5}|_\d{2,5}w)(\.[a-z0-9]{3,4})$").unwrap();
+    }
+    let Ok(mut parsed) = Url::parse(url) else {
+        return url.to_string();
+    };
+    let path = SIZE_SUFFIX_RE.replace(parsed.path(), "$1").to_string();
+    parsed.set_path(&path);
+    let kept: Vec<(String, String)> = parsed
+        .query_pairs()
+        .filter(|(key, _)| !SIZE_QUERY_KEYS.contains(&key.to_lowercase().as_str()))
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect();
+    if kept.is_empty() {
+        parsed.set_query(None);
+    } else {
+        parsed.query_pairs_mut().clear().extend_pairs(kept);
+    }
+    parsed.set_fragment(None);
+    parsed.to_string()
+}
+
+/// Query keys CDNs use to pick an image size.
+const SIZE_QUERY_KEYS: &[&str] = &["w", "h", "width", "height", "wid", "hei", "size", "resize", "dpr"];
+
+/// Pixel width encoded in an image URL (`?w=1200`, `_1200x1600`, `_800w`).
+pub(crate) fn image_width_hint(url: &str) -> Option<u32> {
+    lazy_static::lazy_static! {
+        static ref WIDTH_SUFFIX_RE: Regex =
+            Regex::new(r"(?i)_(\d{2,5})(?:x\d{2,5}|w)\.[a-z0-9]{3,4}$").unwrap();
+    }
+    let parsed = Url::parse(url).ok()?;
+    let from_query = parsed
+        .query_pairs()
+        .find(|(key, _)| matches!(key.to_lowercase().as_str(), "w" | "width" | "wid"))
+        .and_then(|(_, value)| value.parse().ok());
+    from_query.or_else(|| {
+        WIDTH_SUFFIX_RE
+            .captures(parsed.path())
+            .and_then(|caps| caps[1].parse().ok())
+    })
+}
+
+/// `[{base, resolutions: [{url, width}]}]`, one entry per canonical image in
+/// first-seen order, resolutions widest first (unknown widths last).
+fn group_image_variants(urls: &[String], srcset: &[(String, Option<u32>)]) -> Vec<Value> {
+    let mut order: Vec<String> = Vec::new();
+    let mut groups: std::collections::HashMap<String, Vec<(String, Option<u32>)>> = std::collections::HashMap::new();
+    let candidates = urls
+        .iter()
+        .map(|url| (url.clone(), image_width_hint(url)))
+        .chain(srcset.iter().cloned());
+    for (url, width) in candidates {
+        let key = canonical_image_key(&url);
+        let group = groups.entry(key.clone()).or_insert_with(|| {
+            order.push(key);
+            Vec::new()
+        });
+        match group.iter_mut().find(|(existing, _)| *existing == url) {
+            Some(existing) => existing.1 = existing.1.or(width),
+            None => group.push((url, width)),
+        }
+    }
+    order
+        .into_iter()
+        .filter_map(|base| {
+            let mut resolutions = groups.remove(&base)?;
+            resolutions.sort_by_key(|(_, width)| std::cmp::Reverse(width.unwrap_or(0)));
+            let resolutions: Vec<Value> = resolutions
+                .into_iter()
+                .map(|(url, width)| json!({ "url": url, "width": width }))
+                .collect();
+            Some(json!({ "base": base, "resolutions": resolutions }))
+        })
+        .collect()
+}
+
+/// Lowercased product name and brand from JSON-LD (falling back to og:title),
+/// for matching against image alt text. Hints under 3 characters are dropped.
+fn image_name_hints(structured_data: &Value) -> Vec<String> {
+    let product = structured_data
+        .get("json_ld")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .find(|item| matches!(item.get("@type").and_then(|v| v.as_str()), Some("Product" | "ProductGroup")));
+    let name = product
+        .and_then(|p| p.get("name"))
+        .and_then(|v| v.as_str())
+        .or_else(|| structured_data.pointer("/open_graph/og:title").and_then(|v| v.as_str()));
+    let brand = product.and_then(|p| p.get("brand")).and_then(|brand| {
+        brand
+            .as_str()
+            .or_else(|| brand.get("name").and_then(|v| v.as_str()))
+    });
+    [name, brand]
+        .into_iter()
+        .flatten()
+        .map(|hint| hint.trim().to_lowercase())
+        .filter(|hint| hint.chars().count() >= 3)
+        .collect()
+}
+
+/// Decodes `\/` and `\uXXXX` (surrogate pairs included) in a raw slice of
+/// script JSON, leaving every other character untouched. Works on fragments
+/// that aren't a valid JSON string on their own, e.g. a captured array body
+/// `"https:\/\/cdn\/a.jpg","https:\/\/cdn\/b.jpg"`.
+fn unescape_json_fragment(raw: &str) -> String {
+    fn hex4(chars: &mut std::iter::Peekable<std::str::Chars>) -> Option<u32> {
+        let digits: String = chars.by_ref().take(4).collect();
+        if digits.len() != 4 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        u32::from_str_radix(&digits, 16).ok()
+    }
+
+    let mut out = String::with_capacity(raw.len());
+    let mut chars = raw.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.peek() {
+            Some('/') => {
+                chars.next();
+                out.push('/');
+            }
+            Some('u') => {
+                let mut lookahead = chars.clone();
+                lookahead.next();
+                let decoded = hex4(&mut lookahead).and_then(|high| {
+                    if !(0xD800..0xDC00).contains(&high) {
+                        return char::from_u32(high);
+                    }
+                    // High surrogate: needs a following \uDC00-\uDFFF.
+                    if lookahead.next() != Some('\\') || lookahead.next() != Some('u') {
+                        return None;
+                    }
+                    let low = hex4(&mut lookahead).filter(|low| (0xDC00..0xE000).contains(low))?;
+                    char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00))
+                });
+                match decoded {
+                    Some(decoded) => {
+                        out.push(decoded);
+                        chars = lookahead;
+                    }
+                    None => out.push(c),
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Pushes a JSON-LD object onto `out` when it is a product or offer, or each
+/// such member of its `@graph`.
+fn collect_json_ld_products(data: &Value, out: &mut Vec<Value>) {
+    let Some(obj) = data.as_object() else {
+        return;
+    };
+    if let Some(t) = obj.get("@type").and_then(|v| v.as_str()) {
+        if matches!(t, "Product" | "Offer" | "AggregateOffer" | "ProductGroup") {
+            out.push(Value::Object(obj.clone()));
+        }
+    } else if let Some(graph) = obj.get("@graph").and_then(|v| v.as_array()) {
+        for item in graph {
+            if let Some(item_obj) = item.as_object() {
+                if let Some(t) = item_obj.get("@type").and_then(|v| v.as_str()) {
+                    if matches!(t, "Product" | "Offer" | "AggregateOffer" | "ProductGroup") {
+                        out.push(Value::Object(item_obj.clone()));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Parses an ld+json script body, tolerating a BOM, `<!-- -->` wrappers and
+/// trailing junk (e.g. a second concatenated object): on a full-parse failure
+/// the first balanced object/array is used.
+fn parse_json_ld(text: &str) -> Option<Value> {
+    let mut body = text.trim().trim_start_matches('\u{feff}').trim();
+    if let Some(rest) = body.strip_prefix("<!--") {
+        body = rest.trim();
+    }
+    if let Some(rest) = body.strip_suffix("-->") {
+        body = rest.trim();
+    }
+    if let Ok(value) = serde_json::from_str::<Value>(body) {
+        return Some(value);
+    }
+
+    let start = body.find(['{', '['])?;
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in body[start..].char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' | '[' => depth += 1,
+            '}' | ']' => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    return serde_json::from_str(&body[start..start + i + 1]).ok();
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
 struct ImageInfo {
     src: String,
     alt: String,
     score: i32,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_char_ratio_overrides_the_estimate() {
+        let text = "https://cdn.shop.com/a.jpg";
+        let extractor = ProductDataExtractor::new(50_000).with_token_char_ratio(Some(2.0));
+        assert_eq!(extractor.estimate_tokens(text), 13);
+        let extractor = extractor.with_token_char_ratio(None);
+        assert_eq!(extractor.estimate_tokens(text), 14);
+        let extractor = extractor.with_token_char_ratio(Some(0.0));
+        assert_eq!(extractor.token_char_ratio, None);
+    }
+
+    #[test]
+    fn json_ld_tolerates_comment_wrappers_bom_and_trailing_content() {
+        let product = json!({ "@type": "Product", "name": "Tee" });
+        assert_eq!(parse_json_ld(r#"<!-- {"@type": "Product", "name": "Tee"} -->"#), Some(product.clone()));
+        assert_eq!(parse_json_ld("\u{feff}{\"@type\": \"Product\", \"name\": \"Tee\"}"), Some(product.clone()));
+        assert_eq!(
+            parse_json_ld(r#"{"@type": "Product", "name": "Tee"}{"@type": "BreadcrumbList"}"#),
+            Some(product.clone())
+        );
+        assert_eq!(parse_json_ld(r#"{"@type": "Product", "name": "Tee"};"#), Some(product));
+        assert_eq!(parse_json_ld("<!-- not json -->"), None);
+
+        let html = r#"<html><head><script type="application/ld+json">
+            <!--
+            {"@type": "Product", "name": "Commented Tee"}
+            -->
+            </script></head><body></body></html>"#;
+        let data = ProductDataExtractor::new(50_000).extract_product_data("https://shop.com/p/tee", html);
+        assert_eq!(data.pointer("/structured_data/json_ld/0/name"), Some(&json!("Commented Tee")));
+    }
+
+    #[test]
+    fn microdata_price_prefers_content_attribute() {
+        let html = r#"<html><body><div itemscope itemtype="https://schema.org/Product">
+            <span itemprop="name">Tee</span>
+            <span itemprop="price" content="49.99">$49,99 incl. VAT</span>
+            <meta itemprop="priceCurrency" content="USD">
+            </div></body></html>"#;
+        let data = ProductDataExtractor::new(50_000).extract_product_data("https://shop.com/p/tee", html);
+        assert_eq!(data["microdata_price"], json!({ "price": "49.99", "currency": "USD" }));
+    }
+
+    #[test]
+    fn json_ld_top_level_array_yields_its_product() {
+        let html = r#"<html><head><script type="application/ld+json">
+            [
+                {"@type": "BreadcrumbList", "itemListElement": []},
+                {"@type": "Product", "name": "Array Tee", "offers": {"price": "25.00"}}
+            ]
+            </script></head><body></body></html>"#;
+        let data = ProductDataExtractor::new(50_000).extract_product_data("https://shop.com/p/tee", html);
+        let products = data.pointer("/structured_data/json_ld").and_then(|v| v.as_array()).unwrap();
+        assert_eq!(products.len(), 1);
+        assert_eq!(products[0]["name"], "Array Tee");
+    }
+
+    /// A page whose `window.__INITIAL_STATE__` script carries `images` as its
+    /// image array, padded past extract_inline_json's minimum script size.
+    fn inline_state_page(images: &str) -> Html {
+        let padding = "x".repeat(600);
+        Html::parse_document(&format!(
+            r#"<html><body><script>window.__INITIAL_STATE__ = {{"product": {{"images": [{}], "description": "{}"}}}};</script></body></html>"#,
+            images, padding
+        ))
+    }
+
+    #[test]
+    fn inline_json_images_unescape_slashes() {
+        assert_eq!(
+            unescape_json_fragment(r#""https:\/\/cdn\/a.jpg","https:\/\/cdn\/b.jpg""#),
+            r#""https://cdn/a.jpg","https://cdn/b.jpg""#
+        );
+        assert_eq!(unescape_json_fragment(r"https:\u002F\u002Fcdn\u002Fc.png"), "https://cdn/c.png");
+
+        let document = inline_state_page(r#""https:\/\/cdn.shop.com\/a.jpg","https:\/\/cdn.shop.com\/b.jpg""#);
+        assert_eq!(
+            ProductDataExtractor::new(50_000).extract_inline_json(&document),
+            ["https://cdn.shop.com/a.jpg", "https://cdn.shop.com/b.jpg"]
+        );
+    }
+
+    #[test]
+    fn image_matchers_accept_query_strings_and_avif() {
+        let extractor = ProductDataExtractor::new(50_000);
+        let document = inline_state_page(
+            r#""https://cdn.shop.com/a.jpg?v=2","https://cdn.shop.com/b.avif","https://cdn.shop.com/c.svg""#,
+        );
+        assert_eq!(
+            extractor.extract_inline_json(&document),
+            ["https://cdn.shop.com/a.jpg?v=2", "https://cdn.shop.com/b.avif"]
+        );
+
+        let document = Html::parse_document(
+            r#"<html><head>
+            <link rel="preload" as="image" href="/media/hero.jpg?v=2">
+            <link rel="preload" as="image" href="https://cdn.shop.com/b.AVIF">
+            <link rel="preload" as="image" href="/media/sprite.svg">
+            <link rel="preload" as="script" href="/app.js?x=.jpg">
+            </head><body></body></html>"#,
+        );
+        assert_eq!(
+            extractor.extract_preload_images(&document, "https://shop.com/p/tee"),
+            ["https://shop.com/media/hero.jpg?v=2", "https://cdn.shop.com/b.AVIF"]
+        );
+    }
+
+    #[test]
+    fn script_string_holding_closing_tag_stays_in_the_script() {
+        let html = r#"<html><body><script>var x="</script>";</script><div id="after">kept</div></body></html>"#;
+        let sanitized = sanitize_script_boundaries(html);
+        assert!(sanitized.contains(r#"<script>var x="<\/script>";</script>"#));
+
+        let document = Html::parse_document(&sanitized);
+        let scripts: Vec<String> = document
+            .select(&Selector::parse("script").unwrap())
+            .map(|script| script.text().collect())
+            .collect();
+        assert_eq!(scripts, [r#"var x="<\/script>";"#]);
+        let after = document.select(&Selector::parse("#after").unwrap()).next().unwrap();
+        assert_eq!(after.text().collect::<String>(), "kept");
+        // Nothing of the script leaks out as page text.
+        let body: String = document.select(&Selector::parse("body").unwrap()).next().unwrap().text().collect();
+        assert_eq!(body, r#"var x="<\/script>";kept"#);
+    }
+
+    #[test]
+    fn script_regex_literals_and_unclosed_strings_keep_browser_boundaries() {
+        // A quote inside a regex literal doesn't open a string.
+        let html = r#"<script>a.replace(/"/g,"&quot;")</script><div id="after">kept</div>"#;
+        assert_eq!(sanitize_script_boundaries(html), html);
+        let html = r#"<script>var n = a / b; var r = /['"]/.test(s)</script><p>kept</p>"#;
+        assert_eq!(sanitize_script_boundaries(html), html);
+        // No closing tag in code state: split where a browser would.
+        let html = r#"<script>var s = "oops</script><p>kept</p>"#;
+        assert_eq!(sanitize_script_boundaries(html), html);
+    }
+
+    #[test]
+    fn numbered_shots_keep_distinct_image_keys() {
+        assert_ne!(
+            canonical_image_key("https://cdn.com/jacket_0001.jpg"),
+            canonical_image_key("https://cdn.com/jacket_0002.jpg")
+        );
+        assert_ne!(
+            canonical_image_key("https://cdn.com/IMG_1234.jpg"),
+            canonical_image_key("https://cdn.com/IMG_1235.jpg")
+        );
+        assert_eq!(image_width_hint("https://cdn.com/IMG_1234.jpg"), None);
+        assert_eq!(
+            canonical_image_key("https://cdn.com/x_1200x1600.jpg"),
+            canonical_image_key("https://cdn.com/x_600w.jpg?w=600")
+        );
+
+        let html = r#"<html><head><script type="application/ld+json">
+            {"@type": "Product", "name": "Jacket", "image": [
+                "https://cdn.com/jacket_0001.jpg",
+                "https://cdn.com/jacket_0002.jpg",
+                "https://cdn.com/jacket_0003.jpg"
+            ]}</script></head><body></body></html>"#;
+        let data = ProductDataExtractor::new(50_000).extract_product_data("https://shop.com/p/jacket", html);
+        let images: Vec<&str> = data["images"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|img| img["src"].as_str())
+            .collect();
+        assert_eq!(
+            images,
+            [
+                "https://cdn.com/jacket_0001.jpg",
+                "https://cdn.com/jacket_0002.jpg",
+                "https://cdn.com/jacket_0003.jpg",
+            ]
+        );
+    }
+}
@@ -1,9 +1,19 @@
-mod html_extractor;
+// Page details, diagnostics and metrics are only read when building the Python
+// result dicts; without the bindings the Rust API returns just the product.
+#![cfg_attr(not(feature = "python"), allow(dead_code))]
+
+mod fixtures;
+pub mod html_extractor;
+#[cfg(feature = "python")]
+mod logging;
+#[cfg(feature = "python")]
+mod python;
+mod robots;
 
-use pyo3::prelude::*;
-use pyo3::types::PyDict;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use std::process::Command;
@@ -12,45 +22,607 @@ use tokio::time::timeout;
 use scraper::Html;
 use url::Url;
 use lazy_static::lazy_static;
+use lru::LruCache;
 use regex::Regex;
 
+use crate::fixtures::Fixtures;
+use futures::future::{BoxFuture, FutureExt};
 use crate::html_extractor::ProductDataExtractor;
+use crate::robots::RobotsRules;
 
 // ==================== CONFIG ====================
 
+/// The `User-agent` name robots.txt groups must use to address this scraper
+/// under `respect_robots`; the browser UA we send is never matched.
+const ROBOTS_PRODUCT_TOKEN: &str = "rust_scraper";
+
 const MOBILE_UA: &str = "Mozilla/5.0 (Linux; Android 13; Pixel 7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/131.0.0.0 Mobile Safari/537.36";
 
+/// Encodings we can decode (wreq's gzip/deflate/brotli features). Sent explicitly so
+/// emulation defaults never advertise one we can't decompress, e.g. zstd.
+const ACCEPT_ENCODING: &str = "gzip, deflate, br";
+
+/// Sources treated as authoritative: they win merges and end the race's grace
+/// wait. `STRONG_SOURCES` (comma-separated) adds to this list.
+const DEFAULT_STRONG_SOURCES: &[&str] = &[
+    "curlcffi_gemini",
+    "curlcffi_gemini_proxy",
+    "requests_gemini",
+    "cloudflare_gemini",
+    "shopify_json",
+];
+
+const DEFAULT_MAX_PLAUSIBLE_PRICE: i64 = 10_000_000;
+const DEFAULT_IMAGES_ENOUGH: usize = 3;
+const DEFAULT_MIN_IMAGE_EDGE: u32 = 400;
+const DEFAULT_FETCH_CACHE_TTL_SECS: u64 = 300;
+const DEFAULT_FETCH_RETRIES: u32 = 2;
+const DEFAULT_FETCH_ATTEMPT_TIMEOUT_SECS: u64 = 10;
+const DEFAULT_CURL_IMPERSONATE_BIN: &str = "/opt/curl_chrome131_android";
+const DEFAULT_CURL_IMPERSONATE_DOMAINS: &[&str] = &["therealreal.com"];
+// First retry delay; doubles on each further attempt.
+const FETCH_RETRY_BASE_DELAY: Duration = Duration::from_millis(250);
+// Cap on an honored Retry-After so one 429 can't eat the whole race.
+const FETCH_RETRY_AFTER_MAX: Duration = Duration::from_secs(5);
+
+const DEFAULT_GEMINI_MODEL: &str = "gemini-flash-lite-latest";
+const DEFAULT_GEMINI_CLASSIFICATION_MODEL: &str = "gemini-2.0-flash";
+const DEFAULT_GEMINI_BASE_URL: &str = "https://generativelanguage.googleapis.com";
+
 fn env_var(name: &str) -> Option<String> {
     std::env::var(name).ok().filter(|s| !s.is_empty())
 }
 
+/// Keys, endpoints and models, resolved once when a `Scraper` is built.
+#[derive(Debug, Clone)]
+pub struct ScraperConfig {
+    genai_api_key: Option<String>,
+    serpapi_key: Option<String>,
+    // Proxy pool for the proxy route (OXYLABS_PROXY_URLS, else OXYLABS_PROXY_URL).
+    proxy_urls: Vec<String>,
+    cloudflare_worker_url: Option<String>,
+    gemini_model: String,
+    gemini_classification_model: String,
+    // Scheme + host (and optional path prefix) for Gemini calls (GEMINI_BASE_URL).
+    gemini_base_url: String,
+    // Download budget per scrape; once spent, remaining fetches are skipped.
+    max_bytes_per_scrape: Option<u64>,
+    strong_sources: Vec<String>,
+    // Upper bound for a merged price, in minor units (MAX_PLAUSIBLE_PRICE).
+    max_plausible_price: i64,
+    // Image count at which the SerpAPI image approaches are skipped (IMAGES_ENOUGH).
+    images_enough: usize,
+    // HEAD-check final image URLs and drop dead ones (VALIDATE_IMAGE_URLS=1).
+    validate_image_urls: bool,
+    // Drop final images whose real shorter edge is below this many px, read
+    // from the file header (CHECK_IMAGE_DIMENSIONS=1, MIN_IMAGE_EDGE); None = off.
+    min_image_edge: Option<u32>,
+    // Per-approach time limits (APPROACH_TIMEOUTS); unlisted approaches are
+    // bounded only by the overall race timeout.
+    approach_timeouts: HashMap<String, Duration>,
+    // Random pause range in ms before origin fetches (FETCH_JITTER_MS); None = no delay.
+    fetch_jitter_ms: Option<(u64, u64)>,
+    // Serve every network call from fixture files instead (MOCK_FIXTURES_DIR).
+    mock_fixtures_dir: Option<String>,
+    // Age after which cached HTML is refetched (FETCH_CACHE_TTL_SECS).
+    fetch_cache_ttl: Duration,
+    // Extra attempts on 429/5xx/timeouts for the Chrome fetch (FETCH_RETRIES).
+    fetch_retries: u32,
+    // Time limit for each of those attempts (FETCH_ATTEMPT_TIMEOUT_SECS).
+    fetch_attempt_timeout: Duration,
+    // curl-impersonate binary for blocked hard domains (CURL_IMPERSONATE_BIN).
+    curl_impersonate_bin: String,
+    // Hosts (and their subdomains) that fall back to curl-impersonate when the
+    // Chrome fetch is refused (CURL_IMPERSONATE_DOMAINS).
+    curl_impersonate_domains: std::collections::HashSet<String>,
+}
+
+impl ScraperConfig {
+    /// Reads every knob from the environment (see the README's variable list).
+    pub fn from_env() -> Self {
+        Self {
+            genai_api_key: env_var("GENAI_API_KEY"),
+            serpapi_key: env_var("SERPAPI_KEY"),
+            proxy_urls: proxy_urls_from_env(),
+            cloudflare_worker_url: env_var("CLOUDFLARE_WORKER_URL"),
+            gemini_model: DEFAULT_GEMINI_MODEL.to_string(),
+            gemini_classification_model: DEFAULT_GEMINI_CLASSIFICATION_MODEL.to_string(),
+            gemini_base_url: env_var("GEMINI_BASE_URL")
+                .map(|url| url.trim_end_matches('/').to_string())
+                .unwrap_or_else(|| DEFAULT_GEMINI_BASE_URL.to_string()),
+            max_bytes_per_scrape: env_var("MAX_BYTES_PER_SCRAPE").and_then(|v| v.parse().ok()),
+            strong_sources: strong_sources_from_env(),
+            max_plausible_price: env_var("MAX_PLAUSIBLE_PRICE")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_MAX_PLAUSIBLE_PRICE),
+            images_enough: env_var("IMAGES_ENOUGH")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_IMAGES_ENOUGH),
+            validate_image_urls: matches!(
+                env_var("VALIDATE_IMAGE_URLS").as_deref(),
+                Some("1" | "true" | "yes")
+            ),
+            min_image_edge: matches!(
+                env_var("CHECK_IMAGE_DIMENSIONS").as_deref(),
+                Some("1" | "true" | "yes")
+            )
+            .then(|| {
+                env_var("MIN_IMAGE_EDGE")
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(DEFAULT_MIN_IMAGE_EDGE)
+            }),
+            approach_timeouts: approach_timeouts_from_env(),
+            fetch_jitter_ms: env_var("FETCH_JITTER_MS").and_then(|v| parse_jitter_range(&v)),
+            mock_fixtures_dir: env_var("MOCK_FIXTURES_DIR"),
+            fetch_cache_ttl: Duration::from_secs(
+                env_var("FETCH_CACHE_TTL_SECS")
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(DEFAULT_FETCH_CACHE_TTL_SECS),
+            ),
+            fetch_retries: env_var("FETCH_RETRIES")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_FETCH_RETRIES),
+            fetch_attempt_timeout: Duration::from_secs(
+                env_var("FETCH_ATTEMPT_TIMEOUT_SECS")
+                    .and_then(|v| v.parse().ok())
+                    .filter(|&secs: &u64| secs > 0)
+                    .unwrap_or(DEFAULT_FETCH_ATTEMPT_TIMEOUT_SECS),
+            ),
+            curl_impersonate_bin: env_var("CURL_IMPERSONATE_BIN")
+                .unwrap_or_else(|| DEFAULT_CURL_IMPERSONATE_BIN.to_string()),
+            curl_impersonate_domains: curl_impersonate_domains_from_env(),
+        }
+    }
+
+    fn is_strong_source(&self, source: &str) -> bool {
+        self.strong_sources.iter().any(|s| s == source)
+    }
+}
+
+/// `APPROACH_TIMEOUTS=serpapi_images_title=12,curlcffi_gemini=8` (seconds).
+/// Unknown approach names and unparsable entries are logged and ignored.
+fn approach_timeouts_from_env() -> HashMap<String, Duration> {
+    let mut timeouts = HashMap::new();
+    let Some(spec) = env_var("APPROACH_TIMEOUTS") else {
+        return timeouts;
+    };
+    for entry in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let parsed = entry.split_once('=').and_then(|(name, secs)| {
+            let secs: f64 = secs.trim().parse().ok().filter(|s: &f64| s.is_finite() && *s > 0.0)?;
+            let name = name.trim();
+            APPROACHES.contains(&name).then(|| (name.to_string(), Duration::from_secs_f64(secs)))
+        });
+        match parsed {
+            Some((name, limit)) => {
+                timeouts.insert(name, limit);
+            }
+            None => log::warn!("ignoring invalid APPROACH_TIMEOUTS entry '{}'", entry),
+        }
+    }
+    timeouts
+}
+
+/// Longest pre-fetch delay FETCH_JITTER_MS may ask for; larger bounds are clamped.
+const MAX_FETCH_JITTER_MS: u64 = 10_000;
+
+/// "100,400" → (100, 400); a single value is a fixed delay. Bounds are sorted
+/// and clamped to MAX_FETCH_JITTER_MS.
+fn parse_jitter_range(raw: &str) -> Option<(u64, u64)> {
+    let mut bounds = raw.split(',').map(|part| part.trim().parse::<u64>());
+    let low = bounds.next()?.ok()?;
+    let high = match bounds.next() {
+        Some(high) => high.ok()?,
+        None => low,
+    };
+    if bounds.next().is_some() {
+        return None;
+    }
+    let (low, high) = (low.min(MAX_FETCH_JITTER_MS), high.min(MAX_FETCH_JITTER_MS));
+    Some((low.min(high), low.max(high)))
+}
+
+/// `CURL_IMPERSONATE_DOMAINS=therealreal.com,ssense.com`; replaces the default
+/// list. A leading "www." is dropped.
+fn curl_impersonate_domains_from_env() -> std::collections::HashSet<String> {
+    match env_var("CURL_IMPERSONATE_DOMAINS") {
+        Some(domains) => domains
+            .split(',')
+            .map(|d| d.trim().trim_start_matches("www.").to_lowercase())
+            .filter(|d| !d.is_empty())
+            .collect(),
+        None => DEFAULT_CURL_IMPERSONATE_DOMAINS.iter().map(|d| d.to_string()).collect(),
+    }
+}
+
+fn proxy_urls_from_env() -> Vec<String> {
+    match env_var("OXYLABS_PROXY_URLS") {
+        Some(urls) => urls
+            .split(',')
+            .map(str::trim)
+            .filter(|u| !u.is_empty())
+            .map(str::to_string)
+            .collect(),
+        None => env_var("OXYLABS_PROXY_URL").into_iter().collect(),
+    }
+}
+
+fn strong_sources_from_env() -> Vec<String> {
+    let mut sources: Vec<String> = DEFAULT_STRONG_SOURCES.iter().map(|s| s.to_string()).collect();
+    if let Some(extra) = env_var("STRONG_SOURCES") {
+        for source in extra.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            if !sources.iter().any(|s| s == source) {
+                sources.push(source.to_string());
+            }
+        }
+    }
+    sources
+}
+
+/// Config plus the HTTP clients built from it; cheap to clone into spawned approaches.
+#[derive(Clone)]
+pub struct ScraperContext {
+    config: Arc<ScraperConfig>,
+    // General-purpose client (Gemini, SerpAPI, worker, plain requests).
+    client: wreq::Client,
+    // Chrome-emulating client for origin fetches.
+    chrome_client: wreq::Client,
+    // Chrome-emulating clients, one per configured proxy; None when no proxy is usable.
+    proxy_pool: Option<Arc<ProxyPool>>,
+    // When set, fetches and API calls read canned responses instead of the network.
+    fixtures: Option<Arc<Fixtures>>,
+    // Page source for the HTML approaches (fixtures in mock mode).
+    fetcher: Arc<dyn HtmlFetcher>,
+    // LLM backend for extraction and classification.
+    classifier: Arc<dyn ProductClassifier>,
+}
+
+/// Proxied clients handed out round-robin, so one rate-limited endpoint
+/// doesn't take down every proxied fetch.
+struct ProxyPool {
+    clients: Vec<wreq::Client>,
+    next: std::sync::atomic::AtomicUsize,
+}
+
+impl ProxyPool {
+    fn new(clients: Vec<wreq::Client>) -> Self {
+        Self { clients, next: std::sync::atomic::AtomicUsize::new(0) }
+    }
+
+    /// Next proxy in rotation, with its index for logging.
+    fn pick(&self) -> (usize, &wreq::Client) {
+        let index = self.next.fetch_add(1, Ordering::Relaxed) % self.clients.len();
+        (index, &self.clients[index])
+    }
+
+    /// The proxy after `index`, or None when the pool has only one.
+    fn after(&self, index: usize) -> Option<(usize, &wreq::Client)> {
+        if self.clients.len() < 2 {
+            return None;
+        }
+        let index = (index + 1) % self.clients.len();
+        Some((index, &self.clients[index]))
+    }
+}
+
+impl ScraperContext {
+    /// Builds the HTTP clients for `config`, with the default backends (wreq
+    /// and Gemini, or the fixture fetcher in mock mode).
+    pub fn new(config: ScraperConfig) -> Result<Self, String> {
+        let client = wreq::Client::builder()
+            .timeout(Duration::from_secs(15))
+            .gzip(true)
+            .deflate(true)
+            .brotli(true)
+            .build()
+            .map_err(|e| e.to_string())?;
+
+        let chrome_client = wreq::Client::builder()
+            .emulation(wreq_util::Emulation::Chrome131)
+            .gzip(true)
+            .deflate(true)
+            .brotli(true)
+            .build()
+            .map_err(|e| e.to_string())?;
+
+        let mut proxy_clients = Vec::new();
+        for (index, proxy_url) in config.proxy_urls.iter().enumerate() {
+            match wreq::Proxy::all(proxy_url.as_str()) {
+                Ok(proxy) => proxy_clients.push(
+                    wreq::Client::builder()
+                        .emulation(wreq_util::Emulation::Chrome131)
+                        .proxy(proxy)
+                        .gzip(true)
+                        .deflate(true)
+                        .brotli(true)
+                        .build()
+                        .map_err(|e| e.to_string())?,
+                ),
+                Err(e) => log::warn!("invalid proxy url #{}, skipped: {}", index, e),
+            }
+        }
+        if !config.proxy_urls.is_empty() && proxy_clients.is_empty() {
+            log::warn!("no usable proxy url, proxy approach disabled");
+        }
+        let proxy_pool = (!proxy_clients.is_empty()).then(|| Arc::new(ProxyPool::new(proxy_clients)));
+
+        let fixtures = config.mock_fixtures_dir.as_deref().map(|dir| {
+            log::info!("mock mode: serving responses from {}", dir);
+            Arc::new(Fixtures::new(dir))
+        });
+        let fetcher: Arc<dyn HtmlFetcher> = match &fixtures {
+            Some(fixtures) => fixtures.clone(),
+            None => Arc::new(WreqFetcher),
+        };
+
+        Ok(Self {
+            config: Arc::new(config),
+            client,
+            chrome_client,
+            proxy_pool,
+            fixtures,
+            fetcher,
+            classifier: Arc::new(GeminiClassifier),
+        })
+    }
+
+    /// Like `new`, but the HTML approaches fetch through `fetcher` and the LLM
+    /// calls go to `classifier`.
+    pub fn with_backends(
+        config: ScraperConfig,
+        fetcher: Arc<dyn HtmlFetcher>,
+        classifier: Arc<dyn ProductClassifier>,
+    ) -> Result<Self, String> {
+        Ok(Self {
+            fetcher,
+            classifier,
+            ..Self::new(config)?
+        })
+    }
+
+    fn gemini_url(&self, model: &str) -> Option<String> {
+        let key = self.config.genai_api_key.as_deref()?;
+        Some(format!(
+            "{}/v1beta/models/{}:generateContent?key={}",
+            self.config.gemini_base_url, model, key
+        ))
+    }
+}
+
+// ==================== BACKENDS ====================
+
+/// Transport an HTML approach fetches through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FetchRoute {
+    /// Chrome-emulating client, curl-impersonate fallback (curlcffi_gemini).
+    Chrome,
+    /// Chrome-emulating client through the configured proxy (curlcffi_gemini_proxy).
+    Proxy,
+    /// Plain client (requests_gemini).
+    Plain,
+}
+
+/// Where the HTML approaches get page HTML. Implementations own redirect,
+/// login-wall and byte-budget handling and return None for anything unusable.
+pub trait HtmlFetcher: Send + Sync {
+    fn fetch<'a>(
+        &'a self,
+        route: FetchRoute,
+        url: &'a str,
+        state: &'a ScrapeState,
+        ctx: &'a ScraperContext,
+    ) -> BoxFuture<'a, Option<String>>;
+}
+
+/// LLM calls that turn page data, a bare URL, or a search result title into
+/// product fields (merge_data-shaped maps).
+pub trait ProductClassifier: Send + Sync {
+    fn extract<'a>(
+        &'a self,
+        url: &'a str,
+        data: &'a serde_json::Value,
+        state: &'a ScrapeState,
+        ctx: &'a ScraperContext,
+    ) -> BoxFuture<'a, Option<HashMap<String, serde_json::Value>>>;
+
+    fn classify_url<'a>(
+        &'a self,
+        url: &'a str,
+        state: &'a ScrapeState,
+        ctx: &'a ScraperContext,
+    ) -> BoxFuture<'a, Option<HashMap<String, serde_json::Value>>>;
+
+    fn classify_title<'a>(
+        &'a self,
+        url: &'a str,
+        title: &'a str,
+        snippet: Option<&'a str>,
+        state: &'a ScrapeState,
+        ctx: &'a ScraperContext,
+    ) -> BoxFuture<'a, Option<HashMap<String, serde_json::Value>>>;
+}
+
+/// Default fetcher: the wreq clients in `ScraperContext`.
+struct WreqFetcher;
+
+impl HtmlFetcher for WreqFetcher {
+    fn fetch<'a>(
+        &'a self,
+        route: FetchRoute,
+        url: &'a str,
+        state: &'a ScrapeState,
+        ctx: &'a ScraperContext,
+    ) -> BoxFuture<'a, Option<String>> {
+        match route {
+            FetchRoute::Chrome => fetch_html_curlcffi(url, state, ctx).boxed(),
+            FetchRoute::Proxy => fetch_html_curlcffi_proxy(url, state, ctx).boxed(),
+            FetchRoute::Plain => fetch_html_plain(url, state, ctx).boxed(),
+        }
+    }
+}
+
+/// Default classifier: the Gemini prompts below.
+struct GeminiClassifier;
+
+impl ProductClassifier for GeminiClassifier {
+    fn extract<'a>(
+        &'a self,
+        url: &'a str,
+        data: &'a serde_json::Value,
+        state: &'a ScrapeState,
+        ctx: &'a ScraperContext,
+    ) -> BoxFuture<'a, Option<HashMap<String, serde_json::Value>>> {
+        call_gemini_for_product_extraction(url, data, state, ctx).boxed()
+    }
+
+    fn classify_url<'a>(
+        &'a self,
+        url: &'a str,
+        state: &'a ScrapeState,
+        ctx: &'a ScraperContext,
+    ) -> BoxFuture<'a, Option<HashMap<String, serde_json::Value>>> {
+        call_gemini_for_fast_classification(url, state, ctx).boxed()
+    }
+
+    fn classify_title<'a>(
+        &'a self,
+        url: &'a str,
+        title: &'a str,
+        snippet: Option<&'a str>,
+        state: &'a ScrapeState,
+        ctx: &'a ScraperContext,
+    ) -> BoxFuture<'a, Option<HashMap<String, serde_json::Value>>> {
+        call_gemini_from_serpapi(url, title, snippet, state, ctx).boxed()
+    }
+}
+
 // ==================== DATA STRUCTURES ====================
 
+/// A parsed price; `amount_cents / 100` reconstructs the exact amount.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Price {
+    /// Whole units; cents are truncated.
+    pub amount: Option<i32>,
+    /// Exact amount in minor units ("$19.99" → 1999).
+    #[serde(default)]
+    pub amount_cents: Option<i64>,
+    pub currency: Option<String>,
+}
+
+impl Price {
+    /// Splits a decimal amount into whole units and rounded minor units.
+    fn from_decimal(value: f64, currency: Option<String>) -> Self {
+        Self {
+            amount: Some(value as i32),
+            amount_cents: Some((value * 100.0).round() as i64),
+            currency,
+        }
+    }
+}
+
+/// One component of a bundle/set page, from its own JSON-LD Product.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-struct Price {
-    amount: Option<i32>,
-    currency: Option<String>,
+pub struct BundleItem {
+    pub name: String,
+    pub price: Price,
 }
 
+/// The merged product a scrape returns.
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
-struct ProductData {
-    product_name: Option<String>,
-    brand: Option<String>,
-    price: Option<Price>,
-    image_urls: Vec<String>,
-    garment_type: Option<String>,
-    availability: Option<String>,
+pub struct ProductData {
+    pub product_name: Option<String>,
+    pub brand: Option<String>,
+    pub price: Option<Price>,
+    /// Source value `price` was parsed from ("Was $50.00 Now $25.00"), for auditing.
+    #[serde(skip)]
+    pub price_raw: Option<String>,
+    pub image_urls: Vec<String>,
+    pub garment_type: Option<String>,
+    pub availability: Option<String>,
+    /// Lowercased as the source gave it: usually "male" | "female" | "unisex" |
+    /// "kids", but Gemini's value is passed through unvalidated.
+    pub gender: Option<String>,
+    pub original_price: Option<Price>,
+    pub on_sale: Option<bool>,
+    /// Raw ISO string from JSON-LD releaseDate/datePublished.
+    pub release_date: Option<String>,
+    /// {"headers": [...], "rows": [[...]]} from a size-guide table.
+    pub size_chart: Option<serde_json::Value>,
+    /// Sizes as the store writes them ("Small", "EU 38", ...).
+    pub sizes: Vec<String>,
+    /// Price intentionally hidden behind a login ("Sign in to see price").
+    pub price_gated: bool,
+    /// Shipping cost / delivery estimate as shown on the page; informational only.
+    pub shipping_info: Option<String>,
+    /// Canonical fit descriptor ("slim", "regular", "oversized", ...), see FIT_VOCABULARY.
+    pub fit: Option<String>,
+    /// Color name as the store writes it ("Navy", "Off-White").
+    pub color: Option<String>,
+    /// Every color the product is offered in, as the store writes them.
+    pub colors: Vec<String>,
+    /// Color of the displayed variant; the only color when there's just one.
+    pub selected_color: Option<String>,
+    /// Priced components when the page lists several JSON-LD Products.
+    pub bundle_items: Vec<BundleItem>,
+    /// Sum of bundle_items; None unless they all share one currency.
+    pub bundle_total: Option<Price>,
 }
 
 impl ProductData {
+    /// A gated price counts as resolved: there's no price to wait for.
+    fn has_price(&self) -> bool {
+        self.price_gated || self.price.as_ref().and_then(|p| p.amount).is_some()
+    }
+
     fn is_complete(&self) -> bool {
         self.product_name.is_some()
             && self.brand.is_some()
-            && self.price.as_ref().and_then(|p| p.amount).is_some()
+            && self.has_price()
             && !self.image_urls.is_empty()
             && self.garment_type.is_some()
     }
 
+    /// `{name_missing, brand_missing, price_missing, image_missing, unsupported}`
+    /// and whether the four core fields were all found.
+    fn status_flags(&self) -> (serde_json::Value, bool) {
+        let name_missing = self.product_name.is_none();
+        let brand_missing = self.brand.is_none();
+        // A login-gated price is an explicit state, not a missing field.
+        let price_missing = !self.has_price();
+        let image_missing = self.image_urls.is_empty();
+        let success = !(name_missing || brand_missing || price_missing || image_missing);
+        let flags = serde_json::json!({
+            "name_missing": name_missing,
+            "brand_missing": brand_missing,
+            "price_missing": price_missing,
+            "image_missing": image_missing,
+            "unsupported": self.garment_type.as_deref() == Some("unsupported"),
+        });
+        (flags, success)
+    }
+
+    /// The product as a JSON object, plus `missing_flags` and `success`, for
+    /// logging and golden-file comparisons.
+    pub fn to_json(&self) -> String {
+        let mut value = serde_json::to_value(self).unwrap_or_default();
+        if let Some(fields) = value.as_object_mut() {
+            let (flags, success) = self.status_flags();
+            fields.insert("missing_flags".to_string(), flags);
+            fields.insert("success".to_string(), serde_json::Value::Bool(success));
+        }
+        value.to_string()
+    }
+
+    /// A single available color is necessarily the displayed one; otherwise
+    /// falls back to `color`.
+    fn fill_selected_color(&mut self) {
+        if self.selected_color.is_none() {
+            self.selected_color = match self.colors.as_slice() {
+                [only] => Some(only.clone()),
+                _ => self.color.clone(),
+            };
+        }
+    }
+
     fn missing_fields(&self) -> Vec<&str> {
         let mut missing = Vec::new();
         if self.product_name.is_none() {
@@ -59,7 +631,7 @@ impl ProductData {
         if self.brand.is_none() {
             missing.push("brand");
         }
-        if self.price.as_ref().and_then(|p| p.amount).is_none() {
+        if !self.has_price() {
             missing.push("price");
         }
         if self.image_urls.is_empty() {
@@ -70,20 +642,344 @@ impl ProductData {
         }
         missing
     }
+
+    /// Fields whose value differs from a previous result, as `{field: {old, new}}`.
+    /// Only fields present in the baseline are compared: prices by amount+currency,
+    /// images by set membership, everything else by equality.
+    fn changes_since(&self, baseline: &serde_json::Value) -> serde_json::Map<String, serde_json::Value> {
+        let mut changes = serde_json::Map::new();
+        let (Some(baseline), Ok(mut current)) = (baseline.as_object(), serde_json::to_value(self)) else {
+            return changes;
+        };
+        // Results always carry on_sale as a bool.
+        current["on_sale"] = serde_json::Value::Bool(self.on_sale.unwrap_or(false));
+
+        fn price_key(value: &serde_json::Value) -> Option<(Option<i64>, Option<String>)> {
+            if value.is_null() {
+                return None;
+            }
+            let price = parse_price(value);
+            Some((price.amount_cents, price.currency))
+        }
+
+        fn url_set(value: &serde_json::Value) -> std::collections::HashSet<&str> {
+            value
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|v| v.as_str())
+                .collect()
+        }
+
+        for (field, old) in baseline {
+            let Some(new) = current.get(field) else {
+                continue;
+            };
+            let unchanged = match field.as_str() {
+                "price" | "original_price" => price_key(old) == price_key(new),
+                "image_urls" => url_set(old) == url_set(new),
+                _ => old == new,
+            };
+            if !unchanged {
+                changes.insert(field.clone(), serde_json::json!({ "old": old, "new": new }));
+            }
+        }
+        changes
+    }
+}
+
+/// Per-call knobs for a single scrape.
+#[derive(Debug, Clone)]
+struct ScrapeOptions {
+    timeout_sec: f64,
+    include_text_content: bool,
+    // Skip every Gemini/SerpAPI approach; derive fields from structured data and heuristics.
+    deterministic_only: bool,
+    // Previous result to diff against; fills ScrapeOutcome::changes.
+    baseline: Option<serde_json::Value>,
+    // Approach to run alone first; a complete strong result skips the race.
+    primary_approach: Option<String>,
+    // Also return sizes mapped to a canonical scale as sizes_normalized.
+    normalize_sizes: bool,
+    // ISO code the price should be in; a different final currency sets currency_mismatch.
+    expected_currency: Option<String>,
+    // With expected_currency: fail the scrape on a mismatch instead of flagging it.
+    strict_currency: bool,
+    // Also return which source won each field as attribution.
+    include_attribution: bool,
+    // False skips every SerpAPI approach even when SERPAPI_KEY is set.
+    use_serpapi: bool,
+    // Capacity of the process-wide HTML fetch cache; 0 bypasses it.
+    cache_size: usize,
+    // Only these approaches run (names from APPROACHES); None runs them all.
+    enabled_approaches: Option<Vec<String>>,
+    // Re-run one HTML approach through the proxy when the race ends incomplete
+    // with no strong source.
+    retry_with_proxy: bool,
+    // False returns "other"/"unsupported" products instead of raising.
+    raise_on_unsupported: bool,
+    // Also return images_detailed: each final image URL with its alt text.
+    include_image_details: bool,
+    // Output key renames applied to the result dict; unmapped keys stay as-is.
+    field_map: Option<HashMap<String, String>>,
+    // Also return diagnostics: per-approach status and timings.
+    include_diagnostics: bool,
+    // Fetch robots.txt first and skip the origin fetches for disallowed paths.
+    respect_robots: bool,
+}
+
+impl Default for ScrapeOptions {
+    fn default() -> Self {
+        Self {
+            timeout_sec: 30.0,
+            include_text_content: false,
+            deterministic_only: false,
+            baseline: None,
+            primary_approach: None,
+            normalize_sizes: false,
+            expected_currency: None,
+            strict_currency: false,
+            include_attribution: false,
+            use_serpapi: true,
+            cache_size: 0,
+            enabled_approaches: None,
+            retry_with_proxy: false,
+            raise_on_unsupported: true,
+            include_image_details: false,
+            field_map: None,
+            include_diagnostics: false,
+            respect_robots: false,
+        }
+    }
+}
+
+/// Page-level details captured from the HTML approaches (text content from the first one to extract).
+#[derive(Debug, Clone, Default)]
+struct PageInfo {
+    source: Option<String>,
+    text_content: Option<serde_json::Value>,
+    /// Largest pre-trim image count seen across every HTML source.
+    total_image_count: usize,
+    /// "shopify" | "woocommerce" | "magento" | "bigcommerce" | "custom".
+    platform: Option<String>,
+    /// The first HTML source carried JSON-LD, microdata, RDFa or product OG tags.
+    had_structured_data: bool,
+    /// `<html lang>` / og:locale of the first HTML source, normalized ("en-us").
+    locale: Option<String>,
+    /// hreflang alternates of the first HTML source, `{lang: url}`.
+    alternate_urls: serde_json::Map<String, serde_json::Value>,
+    /// Image URL → `<img alt>` across every HTML source; first non-empty alt wins.
+    image_alts: HashMap<String, String>,
+    /// `canonical_image_key` → every `(url, width)` seen for that image across
+    /// HTML sources (srcset candidates and CDN size variants).
+    image_variants: HashMap<String, Vec<(String, Option<u32>)>>,
+}
+
+impl PageInfo {
+    /// A specific platform (from HTML markers or response headers) replaces
+    /// "custom", never the other way round.
+    fn set_platform(&mut self, platform: &str) {
+        if matches!(self.platform.as_deref(), None | Some("custom")) {
+            self.platform = Some(platform.to_string());
+        }
+    }
+}
+
+/// Why the race loop stopped waiting for more approaches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CompletionReason {
+    /// Complete product with at least one field from an HTML+Gemini source.
+    StrongComplete,
+    /// Complete product from weaker sources only, accepted after the 5s grace period.
+    WeakCompleteGrace,
+    /// The overall timeout fired first.
+    Timeout,
+    /// Every approach finished without producing a complete product.
+    Error,
+}
+
+impl CompletionReason {
+    fn as_str(self) -> &'static str {
+        match self {
+            CompletionReason::StrongComplete => "strong_complete",
+            CompletionReason::WeakCompleteGrace => "weak_complete_grace",
+            CompletionReason::Timeout => "timeout",
+            CompletionReason::Error => "error",
+        }
+    }
+}
+
+/// Everything a scrape hands back to the bindings.
+#[derive(Debug, Clone)]
+struct ScrapeOutcome {
+    product: ProductData,
+    page: PageInfo,
+    bytes_downloaded: u64,
+    completion_reason: CompletionReason,
+    changes: Option<serde_json::Map<String, serde_json::Value>>,
+    sizes_normalized: Option<Vec<String>>,
+    currency_mismatch: bool,
+    /// Name/brand share no significant word with the URL slug.
+    likely_mismatch: bool,
+    /// `{approaches: [{name, success, status, elapsed_ms}], total_elapsed_ms,
+    /// timed_out}`, when include_diagnostics was set.
+    diagnostics: Option<serde_json::Value>,
+    /// Field name → winning source, when include_attribution was set.
+    attribution: Option<HashMap<String, String>>,
+    quality_score: u8,
+    /// `(url, alt)` per final image, when include_image_details was set.
+    images_detailed: Option<Vec<(String, Option<String>)>>,
+    /// `(base, [(url, width)])` per final image, widest first, when
+    /// include_image_details was set.
+    images_variants: Option<Vec<(String, Vec<(String, Option<u32>)>)>>,
+}
+
+/// A failed scrape. `partial` keeps whatever fields were merged before the
+/// failure (e.g. a SerpAPI name + price with no garment type) so callers can
+/// decide whether to retry or accept.
+#[derive(Debug, Clone)]
+struct ScrapeFailure {
+    error: ScrapeError,
+    partial: Option<Box<ProductData>>,
+}
+
+impl ScrapeFailure {
+    fn new(error: ScrapeError, product: &ProductData) -> Self {
+        let found_anything = product.product_name.is_some() || product.has_price();
+        Self {
+            error,
+            partial: found_anything.then(|| Box::new(product.clone())),
+        }
+    }
 }
 
+/// Why a scrape failed. With the `python` feature each variant maps to its own
+/// Python exception class (see `python::py_errors`); the Display text keeps the historical
+/// "XxxError: ..." prefixes for callers that still match on messages.
+#[derive(Debug, Clone)]
+pub enum ScrapeError {
+    /// An article/blog page (`og_type` set) or a non-fashion item.
+    NotFashionProduct { url: String, og_type: Option<String> },
+    /// garment_type "other", unrecognized, or never determined.
+    UnsupportedProduct { url: String, garment_type: Option<String> },
+    LoginRequired { url: String, login_url: String },
+    CurrencyMismatch { url: String, expected: String, actual: String },
+    /// The overall timeout fired before any source found the product.
+    Timeout { url: String },
+    /// Every approach finished without finding anything.
+    AllApproachesFailed { url: String },
+    /// No source returned a response at all.
+    NetworkError(String),
+    /// respect_robots was set, robots.txt disallows the URL, and the
+    /// third-party sources found nothing.
+    RobotsDisallowed { url: String },
+}
+
+impl std::fmt::Display for ScrapeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScrapeError::NotFashionProduct { url, og_type: Some(og_type) } => write!(
+                f,
+                "NotFashionProductError: The page at {} is not a product page (og:type: {})",
+                url, og_type
+            ),
+            ScrapeError::NotFashionProduct { url, og_type: None } => write!(
+                f,
+                "NotFashionProductError: The page at {} is not a fashion product page",
+                url
+            ),
+            ScrapeError::UnsupportedProduct { url, garment_type: Some(gtype) } if gtype == "other" => write!(
+                f,
+                "UnsupportedProductError: The product at {} is not a supported fashion item (garment_type: other)",
+                url
+            ),
+            ScrapeError::UnsupportedProduct { url, garment_type } => write!(
+                f,
+                "UnsupportedProductError: Could not determine garment type for product at {} (got: {})",
+                url,
+                garment_type.as_deref().unwrap_or("None")
+            ),
+            ScrapeError::LoginRequired { url, login_url } => write!(
+                f,
+                "LoginRequiredError: The product at {} redirected to a login page ({})",
+                url, login_url
+            ),
+            ScrapeError::CurrencyMismatch { url, expected, actual } => write!(
+                f,
+                "CurrencyMismatchError: The product at {} is priced in {} (expected {})",
+                url, actual, expected
+            ),
+            ScrapeError::Timeout { url } => write!(
+                f,
+                "ScrapeTimeoutError: Timed out before any source found the product at {}",
+                url
+            ),
+            ScrapeError::AllApproachesFailed { url } => write!(
+                f,
+                "AllApproachesFailedError: Every approach failed to find the product at {}",
+                url
+            ),
+            ScrapeError::NetworkError(detail) => write!(f, "NetworkError: {}", detail),
+            ScrapeError::RobotsDisallowed { url } => write!(
+                f,
+                "RobotsDisallowedError: robots.txt disallows fetching {}",
+                url
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ScrapeError {}
+
+/// One scrape's merged fields and bookkeeping, handed to the backends.
 #[derive(Clone)]
-struct ScrapeState {
+pub struct ScrapeState {
     product: Arc<Mutex<ProductData>>,
     field_attribution: Arc<Mutex<HashMap<String, String>>>,
+    page: Arc<Mutex<PageInfo>>,
+    options: Arc<ScrapeOptions>,
+    bytes_downloaded: Arc<AtomicU64>,
+    config: Arc<ScraperConfig>,
+    // Where an HTML fetch was bounced to a login page, if any.
+    login_redirect: Arc<Mutex<Option<String>>>,
+    // og:type of a page that looked like an article/website rather than a product.
+    non_product_og_type: Arc<Mutex<Option<String>>>,
+    completion_reason: Arc<Mutex<Option<CompletionReason>>>,
+    // Set for the retry_with_proxy pass: Chrome/plain fetches go through the proxy.
+    force_proxy: Arc<AtomicBool>,
+    // Every approach run that finished (or hit its own timeout), in finish order.
+    approach_runs: Arc<Mutex<Vec<ApproachRun>>>,
+    // The host's robots.txt rules, fetched once when respect_robots is set.
+    robots: Arc<Mutex<Option<RobotsRules>>>,
     start_time: Instant,
 }
 
+/// One approach's result for the diagnostics summary.
+#[derive(Debug, Clone)]
+struct ApproachRun {
+    name: String,
+    success: bool,
+    /// "success" | "failed" | "timeout" (APPROACH_TIMEOUTS) | "cancelled" (still
+    /// running when the race stopped).
+    status: &'static str,
+    elapsed_ms: u64,
+}
+
 impl ScrapeState {
-    fn new() -> Self {
+    fn new(options: ScrapeOptions, config: Arc<ScraperConfig>) -> Self {
         Self {
             product: Arc::new(Mutex::new(ProductData::default())),
             field_attribution: Arc::new(Mutex::new(HashMap::new())),
+            page: Arc::new(Mutex::new(PageInfo::default())),
+            options: Arc::new(options),
+            bytes_downloaded: Arc::new(AtomicU64::new(0)),
+            config,
+            login_redirect: Arc::new(Mutex::new(None)),
+            non_product_og_type: Arc::new(Mutex::new(None)),
+            completion_reason: Arc::new(Mutex::new(None)),
+            force_proxy: Arc::new(AtomicBool::new(false)),
+            approach_runs: Arc::new(Mutex::new(Vec::new())),
+            robots: Arc::new(Mutex::new(None)),
             start_time: Instant::now(),
         }
     }
@@ -92,42 +988,213 @@ impl ScrapeState {
         self.start_time.elapsed().as_millis()
     }
 
+    /// robots.txt was loaded (respect_robots) and disallows this URL's path.
+    async fn robots_disallows(&self, url: &str) -> bool {
+        let Some(rules) = self.robots.lock().await.clone() else {
+            return false;
+        };
+        let Ok(parsed) = Url::parse(url) else {
+            return false;
+        };
+        let path = match parsed.query() {
+            Some(query) => format!("{}?{}", parsed.path(), query),
+            None => parsed.path().to_string(),
+        };
+        !rules.is_allowed(&path)
+    }
+
+    /// Counts `len` downloaded bytes towards MAX_BYTES_PER_SCRAPE.
+    pub fn record_bytes(&self, len: usize) {
+        self.bytes_downloaded.fetch_add(len as u64, Ordering::Relaxed);
+    }
+
+    fn bytes_downloaded(&self) -> u64 {
+        self.bytes_downloaded.load(Ordering::Relaxed)
+    }
+
+    async fn record_login_redirect(&self, source: &str, final_url: &str) {
+        log::info!(
+            "{} redirected to login page {}, discarding body",
+            source, final_url
+        );
+        let mut login_redirect = self.login_redirect.lock().await;
+        if login_redirect.is_none() {
+            *login_redirect = Some(final_url.to_string());
+        }
+    }
+
+    /// True once MAX_BYTES_PER_SCRAPE has been spent; fetches check this before
+    /// going to the network.
+    pub fn byte_budget_exhausted(&self, what: &str) -> bool {
+        let Some(budget) = self.config.max_bytes_per_scrape else {
+            return false;
+        };
+        let used = self.bytes_downloaded();
+        if used < budget {
+            return false;
+        }
+        log::info!(
+            "byte budget exhausted ({} >= {} bytes), skipping {}",
+            used, budget, what
+        );
+        true
+    }
+
+    /// Keep page-level details from the first HTML source only, so the
+    /// result describes one consistent page. The image count is the max
+    /// across sources.
+    async fn record_page(&self, extracted: &serde_json::Value, source: &str) {
+        let mut page = self.page.lock().await;
+        if let Some(count) = extracted.get("total_image_count").and_then(|v| v.as_u64()) {
+            page.total_image_count = page.total_image_count.max(count as usize);
+        }
+        if let Some(platform) = extracted.get("platform").and_then(|v| v.as_str()) {
+            page.set_platform(platform);
+        }
+        for image in extracted.get("images").and_then(|v| v.as_array()).into_iter().flatten() {
+            let (Some(src), Some(alt)) = (
+                image.get("src").and_then(|v| v.as_str()),
+                image.get("alt").and_then(|v| v.as_str()).map(str::trim).filter(|a| !a.is_empty()),
+            ) else {
+                continue;
+            };
+            page.image_alts.entry(src.to_string()).or_insert_with(|| alt.to_string());
+        }
+        for group in extracted.get("image_variants").and_then(|v| v.as_array()).into_iter().flatten() {
+            let Some(base) = group.get("base").and_then(|v| v.as_str()) else {
+                continue;
+            };
+            let variants = page.image_variants.entry(base.to_string()).or_default();
+            for resolution in group.get("resolutions").and_then(|v| v.as_array()).into_iter().flatten() {
+                let Some(url) = resolution.get("url").and_then(|v| v.as_str()) else {
+                    continue;
+                };
+                let width = resolution.get("width").and_then(|v| v.as_u64()).map(|w| w as u32);
+                if !variants.iter().any(|(seen, _)| seen == url) {
+                    variants.push((url.to_string(), width));
+                }
+            }
+        }
+        if page.source.is_some() {
+            return;
+        }
+        page.source = Some(source.to_string());
+        page.text_content = extracted.get("content").cloned();
+        page.had_structured_data = has_structured_data(extracted);
+        page.locale = extracted.get("locale").and_then(|v| v.as_str()).map(str::to_string);
+        if let Some(alternates) = extracted.get("alternate_urls").and_then(|v| v.as_object()) {
+            page.alternate_urls = alternates.clone();
+        }
+    }
+
+    /// Rejects zero/negative amounts and anything above MAX_PLAUSIBLE_PRICE
+    /// minor units (cents) so a malformed source can never clobber a good price.
+    fn plausible_price(&self, price: &Price, field: &str, source: &str) -> bool {
+        let (Some(amount), Some(cents)) = (price.amount, price.amount_cents) else {
+            return false;
+        };
+        // Cents, so sub-unit prices like 0.50 aren't mistaken for zero.
+        if cents > 0 && cents <= self.config.max_plausible_price {
+            return true;
+        }
+        log::warn!(
+            "rejected implausible {} {} {:?} from {}",
+            field, amount, price.currency, source
+        );
+        false
+    }
+
+    /// Merge rank of a source; lower wins a field.
+    fn source_priority(&self, src: &str) -> u8 {
+        // strong sources (html+gemini and shopify_json by default, extended via STRONG_SOURCES)
+        if self.config.is_strong_source(src) {
+            return 0;
+        }
+        match src {
+            // title-based gemini classification
+            "gemini_classification" => 1,
+            // serpapi shopping
+            "serpapi_google" => 2,
+            // fast url classifier
+            "gemini_fast" => 3,
+            // image-only helpers
+            "serpapi_images_url" | "serpapi_images_title" => 4,
+            // deterministic JSON-LD / OG / worker fields (backstop when Gemini fails)
+            "structured_data" | "cloudflare_structured" | "app_state" | "data_layer" => 5,
+            // keyword guesses from name / URL / breadcrumbs
+            "garment_heuristic" => 6,
+            _ => 7,
+        }
+    }
+
+    /// 0–100 summary for auto-accept vs review thresholds:
+    ///
+    /// - completeness, 50: 10 each for product_name, brand, price (or an
+    ///   explicit price gate), image_urls and a supported garment_type
+    /// - source strength, 25: mean over those filled fields of
+    ///   `1 - source_priority / 7`, so strong sources count fully and keyword
+    ///   heuristics barely
+    /// - images, 15: scaled by count up to IMAGES_ENOUGH
+    /// - structured data, 10: the page carried JSON-LD, microdata, RDFa or
+    ///   product OG tags
+    async fn quality_score(&self, product: &ProductData, page: &PageInfo) -> u8 {
+        let core_fields = [
+            ("product_name", product.product_name.is_some()),
+            ("brand", product.brand.is_some()),
+            ("price", product.has_price()),
+            ("image_urls", !product.image_urls.is_empty()),
+            (
+                "garment_type",
+                product.garment_type.as_deref().is_some_and(|t| t != "unsupported"),
+            ),
+        ];
+        let filled: Vec<&str> = core_fields
+            .iter()
+            .filter(|(_, present)| *present)
+            .map(|(field, _)| *field)
+            .collect();
+        let completeness = 10.0 * filled.len() as f64;
+
+        let attribution = self.field_attribution.lock().await;
+        let strength: f64 = filled
+            .iter()
+            .map(|field| match attribution.get(*field) {
+                Some(src) => 1.0 - f64::from(self.source_priority(src)) / 7.0,
+                None => 0.0,
+            })
+            .sum();
+        let source_strength = if filled.is_empty() {
+            0.0
+        } else {
+            25.0 * strength / filled.len() as f64
+        };
+
+        let images_enough = self.config.images_enough.max(1);
+        let images = 15.0 * product.image_urls.len().min(images_enough) as f64 / images_enough as f64;
+        let structured = if page.had_structured_data { 10.0 } else { 0.0 };
+
+        (completeness + source_strength + images + structured).round().min(100.0) as u8
+    }
+
     async fn merge_data(&self, incoming: &HashMap<String, serde_json::Value>, source: &str) {
         let mut product = self.product.lock().await;
         let mut attribution = self.field_attribution.lock().await;
         let mut merged_fields: Vec<&str> = Vec::new();
 
-        fn source_priority(src: &str) -> u8 {
-            match src {
-                // strong html+gemini sources
-                "curlcffi_gemini" | "curlcffi_gemini_proxy" | "requests_gemini" | "cloudflare_gemini" => 0,
-                // title-based gemini classification
-                "gemini_classification" => 1,
-                // serpapi shopping
-                "serpapi_google" => 2,
-                // fast url classifier
-                "gemini_fast" => 3,
-                // image-only helpers
-                "serpapi_images_url" | "serpapi_images_title" => 4,
-                _ => 5,
-            }
-        }
-
-        fn should_override_field(
-            field: &str,
-            source: &str,
-            attribution: &HashMap<String, String>,
-            is_empty: bool,
-        ) -> bool {
+        let should_override_field = |field: &str,
+                                     source: &str,
+                                     attribution: &HashMap<String, String>,
+                                     is_empty: bool|
+         -> bool {
             if is_empty {
                 return true;
             }
             if let Some(existing_src) = attribution.get(field) {
-                source_priority(source) < source_priority(existing_src)
+                self.source_priority(source) < self.source_priority(existing_src)
             } else {
                 false
             }
-        }
+        };
 
         // product_name
         if let Some(name) = incoming
@@ -157,10 +1224,14 @@ impl ScrapeState {
         // price
         if let Some(price_val) = incoming.get("price") {
             let parsed = parse_price(price_val);
-            if parsed.amount.is_some() {
+            if parsed.amount.is_some() && self.plausible_price(&parsed, "price", source) {
                 let is_empty = product.price.as_ref().and_then(|p| p.amount).is_none();
                 if should_override_field("price", source, &attribution, is_empty) {
                     product.price = Some(parsed);
+                    product.price_raw = Some(match incoming.get("price_raw").and_then(|v| v.as_str()) {
+                        Some(raw) => raw.to_string(),
+                        None => price_val.as_str().map(str::to_string).unwrap_or_else(|| price_val.to_string()),
+                    });
                     attribution.insert("price".to_string(), source.to_string());
                     merged_fields.push("price");
                 }
@@ -190,6 +1261,24 @@ impl ScrapeState {
             }
         }
 
+        // sizes (more is better, like images)
+        if let Some(sizes) = incoming.get("sizes").and_then(|v| v.as_array()) {
+            let sizes: Vec<String> = sizes
+                .iter()
+                .filter_map(|v| v.as_str().map(String::from))
+                .collect();
+            let is_empty = product.sizes.is_empty();
+            let should_take = sizes.len() > product.sizes.len()
+                || (sizes.len() == product.sizes.len()
+                    && !sizes.is_empty()
+                    && should_override_field("sizes", source, &attribution, is_empty));
+            if should_take {
+                product.sizes = sizes;
+                attribution.insert("sizes".to_string(), source.to_string());
+                merged_fields.push("sizes");
+            }
+        }
+
         // garment_type
         if let Some(gtype) = incoming.get("garment_type").and_then(|v| v.as_str()) {
             let is_empty = product.garment_type.is_none();
@@ -210,58 +1299,397 @@ impl ScrapeState {
             }
         }
 
-        if !merged_fields.is_empty() {
-            let elapsed = self.elapsed_ms();
-            println!(
-                "[rust_scraper] +{}ms merge_data from {}: {:?}",
-                elapsed, source, merged_fields
-            );
+        // gender
+        if let Some(gender) = incoming.get("gender").and_then(|v| v.as_str()) {
+            let is_empty = product.gender.is_none();
+            if should_override_field("gender", source, &attribution, is_empty) {
+                product.gender = Some(gender.to_string());
+                attribution.insert("gender".to_string(), source.to_string());
+                merged_fields.push("gender");
+            }
         }
-    }
 
-    async fn is_complete(&self) -> bool {
-        self.product.lock().await.is_complete()
-    }
+        // original_price (pre-sale price)
+        if let Some(price_val) = incoming.get("original_price") {
+            let parsed = parse_price(price_val);
+            if parsed.amount.is_some() && self.plausible_price(&parsed, "original_price", source) {
+                let is_empty = product.original_price.as_ref().and_then(|p| p.amount).is_none();
+                if should_override_field("original_price", source, &attribution, is_empty) {
+                    product.original_price = Some(parsed);
+                    attribution.insert("original_price".to_string(), source.to_string());
+                    merged_fields.push("original_price");
+                }
+            }
+        }
 
-    async fn has_strong_source(&self) -> bool {
-        let attribution = self.field_attribution.lock().await;
-        attribution.values().any(|src| {
-            matches!(
-                src.as_str(),
-                "curlcffi_gemini"
-                    | "curlcffi_gemini_proxy"
-                    | "requests_gemini"
-                    | "cloudflare_gemini"
-            )
-        })
-    }
-}
+        // on_sale
+        if let Some(on_sale) = incoming.get("on_sale").and_then(|v| v.as_bool()) {
+            let is_empty = product.on_sale.is_none();
+            if should_override_field("on_sale", source, &attribution, is_empty) {
+                product.on_sale = Some(on_sale);
+                attribution.insert("on_sale".to_string(), source.to_string());
+                merged_fields.push("on_sale");
+            }
+        }
 
-// ==================== UTILITY FUNCTIONS ====================
+        // price_gated: sticky once any source sees a login-gated price
+        if incoming.get("price_gated").and_then(|v| v.as_bool()) == Some(true) && !product.price_gated {
+            product.price_gated = true;
+            attribution.insert("price_gated".to_string(), source.to_string());
+            merged_fields.push("price_gated");
+        }
 
-fn parse_price(value: &serde_json::Value) -> Price {
-    if let Some(obj) = value.as_object() {
-        return Price {
-            amount: obj.get("amount").and_then(|v| v.as_i64()).map(|v| v as i32),
-            currency: obj.get("currency").and_then(|v| v.as_str()).map(String::from),
-        };
-    }
+        // release_date
+        if let Some(date) = incoming.get("release_date").and_then(|v| v.as_str()) {
+            let is_empty = product.release_date.is_none();
+            if should_override_field("release_date", source, &attribution, is_empty) {
+                product.release_date = Some(date.to_string());
+                attribution.insert("release_date".to_string(), source.to_string());
+                merged_fields.push("release_date");
+            }
+        }
 
-    if let Some(num) = value.as_f64() {
-        return Price {
-            amount: Some(num as i32),
-            currency: Some("USD".to_string()),
-        };
-    }
+        // shipping_info
+        if let Some(info) = incoming.get("shipping_info").and_then(|v| v.as_str()) {
+            let is_empty = product.shipping_info.is_none();
+            if should_override_field("shipping_info", source, &attribution, is_empty) {
+                product.shipping_info = Some(info.to_string());
+                attribution.insert("shipping_info".to_string(), source.to_string());
+                merged_fields.push("shipping_info");
+            }
+        }
 
-    if let Some(s) = value.as_str() {
-        return parse_price_string(s);
-    }
+        // bundle_items (more is better, like sizes); the total is derived from them
+        if let Some(items) = incoming.get("bundle_items").and_then(|v| v.as_array()) {
+            let items: Vec<BundleItem> = items
+                .iter()
+                .filter_map(|item| serde_json::from_value(item.clone()).ok())
+                .collect();
+            if items.len() >= 2 && items.len() > product.bundle_items.len() {
+                product.bundle_total = bundle_total(&items);
+                product.bundle_items = items;
+                attribution.insert("bundle_items".to_string(), source.to_string());
+                merged_fields.push("bundle_items");
+            }
+        }
 
-    Price {
-        amount: None,
-        currency: None,
-    }
+        // color
+        if let Some(color) = incoming
+            .get("color")
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|c| !c.is_empty())
+        {
+            let is_empty = product.color.is_none();
+            if should_override_field("color", source, &attribution, is_empty) {
+                product.color = Some(color.to_string());
+                attribution.insert("color".to_string(), source.to_string());
+                merged_fields.push("color");
+            }
+        }
+
+        // colors (more is better, like sizes)
+        if let Some(colors) = incoming.get("colors").and_then(|v| v.as_array()) {
+            let colors: Vec<String> = colors
+                .iter()
+                .filter_map(|v| v.as_str().map(String::from))
+                .collect();
+            let is_empty = product.colors.is_empty();
+            let should_take = colors.len() > product.colors.len()
+                || (colors.len() == product.colors.len()
+                    && !colors.is_empty()
+                    && should_override_field("colors", source, &attribution, is_empty));
+            if should_take {
+                product.colors = colors;
+                attribution.insert("colors".to_string(), source.to_string());
+                merged_fields.push("colors");
+            }
+        }
+
+        // selected_color
+        if let Some(color) = incoming
+            .get("selected_color")
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|c| !c.is_empty())
+        {
+            let is_empty = product.selected_color.is_none();
+            if should_override_field("selected_color", source, &attribution, is_empty) {
+                product.selected_color = Some(color.to_string());
+                attribution.insert("selected_color".to_string(), source.to_string());
+                merged_fields.push("selected_color");
+            }
+        }
+
+        // fit: a deterministic keyword match wins over Gemini's reading and is
+        // never replaced once set.
+        if let Some(fit) = incoming.get("fit").and_then(|v| v.as_str()) {
+            let is_keyword_source = |src: &str| matches!(src, "structured_data" | "cloudflare_structured");
+            let is_empty = product.fit.is_none();
+            let keyword_match = attribution.get("fit").is_some_and(|src| is_keyword_source(src));
+            if !keyword_match
+                && (is_keyword_source(source) || should_override_field("fit", source, &attribution, is_empty))
+            {
+                product.fit = Some(fit.to_string());
+                attribution.insert("fit".to_string(), source.to_string());
+                merged_fields.push("fit");
+            }
+        }
+
+        // size_chart
+        if let Some(chart) = incoming.get("size_chart").filter(|v| v.is_object()) {
+            let is_empty = product.size_chart.is_none();
+            if should_override_field("size_chart", source, &attribution, is_empty) {
+                product.size_chart = Some(chart.clone());
+                attribution.insert("size_chart".to_string(), source.to_string());
+                merged_fields.push("size_chart");
+            }
+        }
+
+        if !merged_fields.is_empty() {
+            let elapsed = self.elapsed_ms();
+            log::info!(
+                event = "merge", source = source, elapsed_ms = elapsed as u64;
+                "+{}ms merge_data from {}: {:?}",
+                elapsed, source, merged_fields
+            );
+        }
+    }
+
+    async fn is_complete(&self) -> bool {
+        self.product.lock().await.is_complete()
+    }
+
+    /// True once image_urls already meets IMAGES_ENOUGH, so the SerpAPI image
+    /// approaches can skip spending quota.
+    async fn has_enough_images(&self, approach: &str) -> bool {
+        let count = self.product.lock().await.image_urls.len();
+        if count < self.config.images_enough {
+            return false;
+        }
+        log::info!(
+            "{} skipped, already have {} images (IMAGES_ENOUGH={})",
+            approach, count, self.config.images_enough
+        );
+        true
+    }
+
+    async fn has_strong_source(&self) -> bool {
+        let attribution = self.field_attribution.lock().await;
+        attribution.values().any(|src| self.config.is_strong_source(src))
+    }
+
+    /// Images are merged and came from a strong source, so an image-only
+    /// helper has nothing left to add.
+    async fn has_strong_images(&self) -> bool {
+        let has_images = !self.product.lock().await.image_urls.is_empty();
+        has_images
+            && self
+                .field_attribution
+                .lock()
+                .await
+                .get("image_urls")
+                .is_some_and(|src| self.config.is_strong_source(src))
+    }
+}
+
+// ==================== UTILITY FUNCTIONS ====================
+
+/// Built-in symbol → ISO code defaults. `CURRENCY_SYMBOLS_PATH` can point at a
+/// JSON object of extra/overriding mappings, e.g. `{"Ft": "HUF"}`.
+/// "$" is ambiguous (CAD/AUD/MXN/...) and maps to USD as a best guess. "kr" is
+/// shared by SEK/NOK/DKK/ISK; it defaults to SEK and "kr." (Danish style) to DKK.
+/// For these `parse_price_string` prefers an ISO code in the same string.
+const CURRENCY_SYMBOLS: &[(&str, &str)] = &[
+    ("CHF", "CHF"),
+    ("US$", "USD"),
+    ("A$", "AUD"),
+    ("C$", "CAD"),
+    ("R$", "BRL"),
+    ("$", "USD"),
+    ("€", "EUR"),
+    ("£", "GBP"),
+    ("¥", "JPY"),
+    ("₹", "INR"),
+    ("kr.", "DKK"),
+    ("kr", "SEK"),
+    ("zł", "PLN"),
+    ("฿", "THB"),
+    ("₩", "KRW"),
+    ("₺", "TRY"),
+];
+
+/// Symbols that only guess at the currency; see CURRENCY_SYMBOLS.
+const AMBIGUOUS_CURRENCY_SYMBOLS: &[&str] = &["$", "kr", "kr."];
+
+/// ISO codes recognized inside free-text prices ("299 NOK", "SEK 1 299").
+const PRICE_STRING_ISO_CODES: &[&str] = &[
+    "USD", "EUR", "GBP", "JPY", "INR", "SEK", "NOK", "DKK", "ISK", "PLN", "BRL", "KRW", "CHF", "TRY",
+    "CAD", "AUD", "NZD", "MXN", "HKD", "SGD", "CNY", "THB", "ZAR", "AED", "SAR", "CZK", "HUF", "RON",
+    "ILS",
+];
+
+lazy_static! {
+    /// Defaults merged with `CURRENCY_SYMBOLS_PATH`, longest symbol first so
+    /// "A$" wins over "$" and "kr." over "kr".
+    static ref CURRENCY_SYMBOL_MAP: Vec<(String, String)> = load_currency_symbols();
+}
+
+fn load_currency_symbols() -> Vec<(String, String)> {
+    let mut map: HashMap<String, String> = CURRENCY_SYMBOLS
+        .iter()
+        .map(|(symbol, code)| (symbol.to_string(), code.to_string()))
+        .collect();
+
+    if let Some(path) = env_var("CURRENCY_SYMBOLS_PATH") {
+        let custom = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|raw| {
+                serde_json::from_str::<HashMap<String, String>>(&raw).map_err(|e| e.to_string())
+            });
+        match custom {
+            Ok(custom) => {
+                for (symbol, code) in custom {
+                    let symbol = symbol.trim().to_string();
+                    let code = code.trim().to_ascii_uppercase();
+                    if symbol.is_empty() || code.len() != 3 {
+                        log::warn!(
+                            "ignoring currency mapping {:?} -> {:?}",
+                            symbol, code
+                        );
+                        continue;
+                    }
+                    map.insert(symbol, code);
+                }
+            }
+            Err(e) => log::warn!(
+                "failed to load CURRENCY_SYMBOLS_PATH={}: {}",
+                path, e
+            ),
+        }
+    }
+
+    let mut symbols: Vec<(String, String)> = map.into_iter().collect();
+    symbols.sort_by(|a, b| {
+        b.0.chars()
+            .count()
+            .cmp(&a.0.chars().count())
+            .then_with(|| a.0.cmp(&b.0))
+    });
+    symbols
+}
+
+/// Normalizes a currency value from JSON-LD, OG tags or Gemini ("usd", "$", "Eur",
+/// "GBP ") to an uppercase ISO 4217 code. Returns None for values that aren't a
+/// code or a known symbol.
+fn normalize_currency_code(raw: &str) -> Option<String> {
+    let trimmed = raw.trim();
+    if trimmed.len() == 3 && trimmed.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Some(trimmed.to_ascii_uppercase());
+    }
+    CURRENCY_SYMBOL_MAP
+        .iter()
+        .find(|(symbol, _)| trimmed.eq_ignore_ascii_case(symbol))
+        .map(|(_, code)| code.clone())
+}
+
+fn parse_price(value: &serde_json::Value) -> Price {
+    if let Some(obj) = value.as_object() {
+        let currency = obj
+            .get("currency")
+            .and_then(|v| v.as_str())
+            .and_then(normalize_currency_code);
+        // Exact cents win over a (possibly truncated) whole-unit amount.
+        if let Some(cents) = obj.get("amount_cents").and_then(|v| v.as_i64()) {
+            return Price {
+                amount: Some((cents / 100) as i32),
+                amount_cents: Some(cents),
+                currency,
+            };
+        }
+        return match obj.get("amount").and_then(|v| v.as_f64()) {
+            Some(amount) => Price::from_decimal(amount, currency),
+            None => Price {
+                amount: None,
+                amount_cents: None,
+                currency,
+            },
+        };
+    }
+
+    if let Some(num) = value.as_f64() {
+        return Price::from_decimal(num, Some("USD".to_string()));
+    }
+
+    if let Some(s) = value.as_str() {
+        return parse_price_string(s);
+    }
+
+    Price {
+        amount: None,
+        amount_cents: None,
+        currency: None,
+    }
+}
+
+/// Words right before an amount that make it a discount or the old price
+/// rather than something to pay ("Save $10", "Was $50").
+const NON_CURRENT_PRICE_PREFIXES: &[&str] = &["save", "was"];
+/// Same, right after the amount ("$5 off").
+const NON_CURRENT_PRICE_SUFFIXES: &[&str] = &["off"];
+
+/// Every symbol- or ISO-code-tagged amount in a multi-amount price string
+/// (a `price_signals` entry such as "Now $25 Was $50"), parsed separately.
+/// Discounts and old prices (NON_CURRENT_PRICE_PREFIXES/SUFFIXES) are skipped.
+fn price_candidates(s: &str) -> Vec<Price> {
+    lazy_static! {
+        static ref PRICE_TOKEN_RE: Regex = Regex::new(&format!(
+            r"(?:R\$|US\$|A\$|C\$|[$£€¥₹₩₺฿])\s*\d[\d,.']*|\b(?:{codes})\s*\d[\d,.']*|\d[\d,.']*\s*(?:(?:{codes})\b|kr\.?|zł)",
+            codes = PRICE_STRING_ISO_CODES.join("|")
+        ))
+        .unwrap();
+    }
+    fn is_marker(word: Option<&String>, markers: &[&str]) -> bool {
+        word.is_some_and(|word| markers.contains(&word.to_lowercase().as_str()))
+    }
+    let words = |text: &str| -> Vec<String> {
+        text.split(|c: char| !c.is_alphabetic())
+            .filter(|word| !word.is_empty())
+            .map(str::to_string)
+            .collect()
+    };
+
+    let matches: Vec<_> = PRICE_TOKEN_RE.find_iter(s).collect();
+    let mut candidates = Vec::new();
+    for (i, m) in matches.iter().enumerate() {
+        // Only the text between neighbouring amounts belongs to this one.
+        let before_start = if i == 0 { 0 } else { matches[i - 1].end() };
+        let after_end = matches.get(i + 1).map_or(s.len(), |next| next.start());
+        let before = words(&s[before_start..m.start()]);
+        let after = words(&s[m.end()..after_end]);
+        if is_marker(before.last(), NON_CURRENT_PRICE_PREFIXES) || is_marker(after.first(), NON_CURRENT_PRICE_SUFFIXES) {
+            continue;
+        }
+        let price = parse_price_string(m.as_str());
+        if price.amount_cents.is_some_and(|cents| cents > 0) {
+            candidates.push(price);
+        }
+    }
+    candidates
+}
+
+/// The current price among several shown for one product: the lowest positive
+/// amount in the first candidate's currency, since a second, higher figure is
+/// nearly always the struck-through original and other currencies are
+/// conversions.
+fn select_current_price(candidates: &[Price]) -> Option<Price> {
+    let currency = &candidates.first()?.currency;
+    candidates
+        .iter()
+        .filter(|price| price.currency == *currency)
+        .filter(|price| price.amount_cents.is_some_and(|cents| cents > 0))
+        .min_by_key(|price| price.amount_cents)
+        .cloned()
 }
 
 fn parse_price_string(s: &str) -> Price {
@@ -274,42 +1702,51 @@ fn parse_price_string(s: &str) -> Price {
         price_str = price_str.replace("Was", "");
     }
 
-    // Currency symbols
-    if price_str.contains("A$") {
-        currency = Some("AUD".to_string());
-        price_str = price_str.replace("A$", "");
-    } else if price_str.contains("C$") {
-        currency = Some("CAD".to_string());
-        price_str = price_str.replace("C$", "");
-    } else if price_str.contains('$') {
-        currency = Some("USD".to_string());
-        price_str = price_str.replace('$', "");
-    } else if price_str.contains('€') {
-        currency = Some("EUR".to_string());
-        price_str = price_str.replace('€', "");
-    } else if price_str.contains('£') {
-        currency = Some("GBP".to_string());
-        price_str = price_str.replace('£', "");
-    } else if price_str.contains('¥') {
-        currency = Some("JPY".to_string());
-        price_str = price_str.replace('¥', "");
-    } else if price_str.contains('₹') {
-        currency = Some("INR".to_string());
-        price_str = price_str.replace('₹', "");
-    }
-
-    // Extract digits
-    price_str = price_str.replace(',', "").trim().to_string();
+    lazy_static! {
+        static ref ISO_CODE_RE: Regex = Regex::new(r"\b[A-Z]{3}\b").unwrap();
+    }
+    let iso_code = ISO_CODE_RE
+        .find_iter(&price_str)
+        .map(|m| m.as_str())
+        .find(|code| PRICE_STRING_ISO_CODES.contains(code))
+        .map(str::to_string);
+
+    // Currency symbols (longest first, so "A$" is matched before "$")
+    if let Some((symbol, code)) = CURRENCY_SYMBOL_MAP
+        .iter()
+        .find(|(symbol, _)| price_str.contains(symbol.as_str()))
+    {
+        currency = Some(code.clone());
+        if AMBIGUOUS_CURRENCY_SYMBOLS.contains(&symbol.as_str()) && iso_code.is_some() {
+            currency = iso_code.clone();
+        }
+        price_str = price_str.replace(symbol.as_str(), "");
+    }
+    if let Some(code) = iso_code {
+        currency = currency.or_else(|| Some(code.clone()));
+        price_str = price_str.replace(code.as_str(), "");
+    }
+
+    // Extract digits (drop thousands separators, including Swiss apostrophes: "1'299.00")
+    price_str = price_str
+        .replace([',', '\'', '’'], "")
+        .trim()
+        .to_string();
     let amount = if price_str.contains('.') {
-        price_str.parse::<f64>().ok().map(|v| v as i32)
+        price_str.parse::<f64>().ok()
     } else {
         let digits: String = price_str.chars().filter(|c| c.is_ascii_digit()).collect();
-        digits.parse::<i32>().ok()
+        digits.parse::<i32>().ok().map(f64::from)
     };
 
-    Price {
-        amount,
-        currency: currency.or_else(|| Some("USD".to_string())),
+    let currency = currency.or_else(|| Some("USD".to_string()));
+    match amount {
+        Some(amount) => Price::from_decimal(amount, currency),
+        None => Price {
+            amount: None,
+            amount_cents: None,
+            currency,
+        },
     }
 }
 
@@ -349,15 +1786,21 @@ fn normalize_url_path(url: &str) -> Option<String> {
     Some(rebuilt.to_string())
 }
 
+/// Query keys that identify the product or the variant shown (price/images
+/// can differ per variant). Demandware's `dwvar_<pid>_<attr>` keys are kept by
+/// prefix; every other key (utm_*, gclid, fbclid, ref, ...) is dropped.
+const PRODUCT_QUERY_KEYS: &[&str] = &[
+    "pid", "productid", "product_id", "id", "item", "itemid", "product_no", "products_id", "main_page",
+    "variant", "sku", "color", "size",
+];
+
 fn clean_product_url(url: &str) -> String {
     if let Ok(mut parsed) = Url::parse(url) {
         let mut kept: Vec<(String, String)> = Vec::new();
         for (k, v) in parsed.query_pairs() {
             let key = k.to_string();
             let key_lower = key.to_lowercase();
-            if ["pid", "productid", "product_id", "id", "item", "itemid", "product_no", "products_id", "main_page"]
-                .contains(&key_lower.as_str())
-            {
+            if key_lower.starts_with("dwvar_") || PRODUCT_QUERY_KEYS.contains(&key_lower.as_str()) {
                 kept.push((key, v.to_string()));
             }
         }
@@ -394,43 +1837,1259 @@ fn urls_match_product(url1: &str, url2: &str) -> bool {
     }
     let norm1 = normalize_url_path(url1).unwrap_or_else(|| url1.to_string());
     let norm2 = normalize_url_path(url2).unwrap_or_else(|| url2.to_string());
-    norm1 == norm2
+    norm1 == norm2 || without_session_segments(&norm1) == without_session_segments(&norm2)
 }
 
-fn fetch_with_curl_impersonate(url: &str) -> Option<String> {
-    let output = Command::new("/opt/curl_chrome131_android")
+/// Path segments that announce the session token in the segment after them
+/// (`/s/ABC123/products/jacket`).
+const SESSION_MARKER_SEGMENTS: &[&str] = &["s", "sid", "session", "sessionid"];
+
+/// The URL with session/tracking path segments removed (see
+/// `is_session_segment`, SESSION_MARKER_SEGMENTS), `sid=...`-style segments
+/// dropped and `;jsessionid=...`-style path parameters cut, so two links to
+/// one product differing only by a session token compare equal.
+fn without_session_segments(url: &str) -> String {
+    let Ok(mut parsed) = Url::parse(url) else {
+        return url.to_string();
+    };
+    let mut segments: Vec<&str> = Vec::new();
+    let mut after_marker = false;
+    for seg in parsed.path().split('/').map(|seg| seg.split(';').next().unwrap_or(seg)) {
+        if seg.is_empty() {
+            continue;
+        }
+        // Short tokens only count as sessions right after a marker.
+        if std::mem::take(&mut after_marker) {
+            continue;
+        }
+        let lower = seg.to_lowercase();
+        if SESSION_MARKER_SEGMENTS.contains(&lower.as_str()) {
+            after_marker = true;
+            continue;
+        }
+        let named_session = lower
+            .split_once('=')
+            .is_some_and(|(key, _)| SESSION_MARKER_SEGMENTS.contains(&key) || key == "jsessionid");
+        if named_session || is_session_segment(seg) {
+            continue;
+        }
+        segments.push(seg);
+    }
+    let path = format!("/{}", segments.join("/"));
+    parsed.set_path(&path);
+    parsed.to_string()
+}
+
+/// High-entropy path segments that identify a session rather than a product:
+/// ASP.NET cookieless `(S(...))` tokens, long hex runs (16+ chars with a
+/// digit), and 20+ char base64-ish tokens mixing upper, lower and digits.
+/// Hyphenated slugs and all-digit product ids never match.
+fn is_session_segment(seg: &str) -> bool {
+    if seg.starts_with("(S(") || seg.starts_with("(s(") {
+        return true;
+    }
+    let has_digit = seg.chars().any(|c| c.is_ascii_digit());
+    let all_digits = seg.chars().all(|c| c.is_ascii_digit());
+    if all_digits || !has_digit {
+        return false;
+    }
+    if seg.len() >= 16 && seg.chars().all(|c| c.is_ascii_hexdigit()) {
+        return true;
+    }
+    seg.len() >= 20
+        && seg.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '=')
+        && seg.chars().any(|c| c.is_ascii_uppercase())
+        && seg.chars().any(|c| c.is_ascii_lowercase())
+}
+
+/// Path words that say nothing about which product a URL names.
+const URL_SLUG_STOPWORDS: &[&str] = &[
+    "the", "and", "for", "with", "product", "products", "item", "items", "shop", "store", "buy",
+    "women", "womens", "men", "mens", "kids", "new", "sale", "html", "htm", "php", "aspx", "detail",
+    "details", "collections", "collection", "catalog", "category", "clothing", "www", "com",
+];
+
+/// Significant lowercase words in `text`: 3+ letters, not all digits, not a
+/// URL_SLUG_STOPWORDS entry.
+fn significant_tokens(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .map(str::to_lowercase)
+        .filter(|token| {
+            token.chars().count() >= 3
+                && !token.chars().all(|c| c.is_ascii_digit())
+                && !URL_SLUG_STOPWORDS.contains(&token.as_str())
+        })
+        .collect()
+}
+
+/// The merged name/brand shares no significant word with the URL's path slug,
+/// a sign SerpAPI or image matching pulled in a different product. Tokens
+/// match on a shared 4+ char prefix ("jacket"/"jackets"). Never true when
+/// either side has nothing to compare (numeric-id URLs, no name).
+fn likely_url_mismatch(url: &str, product: &ProductData) -> bool {
+    let Ok(parsed) = Url::parse(&clean_product_url(url)) else {
+        return false;
+    };
+    let path = urlencoding::decode(parsed.path())
+        .map(|p| p.into_owned())
+        .unwrap_or_else(|_| parsed.path().to_string());
+    let url_tokens: Vec<String> = path
+        .split('/')
+        .filter(|seg| !is_session_segment(seg))
+        .flat_map(significant_tokens)
+        .collect();
+    let product_text = [product.product_name.as_deref(), product.brand.as_deref()]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let product_tokens = significant_tokens(&product_text);
+    if url_tokens.is_empty() || product_tokens.is_empty() {
+        return false;
+    }
+
+    let related = |a: &str, b: &str| {
+        let shared = a.chars().zip(b.chars()).take_while(|(x, y)| x == y).count();
+        a == b || (shared >= 4 && (a.starts_with(b) || b.starts_with(a)))
+    };
+    !url_tokens
+        .iter()
+        .any(|u| product_tokens.iter().any(|p| related(u, p)))
+}
+
+/// Last resort for a refused Chrome fetch: shells out to curl-impersonate when
+/// the host (or a parent domain) is in CURL_IMPERSONATE_DOMAINS.
+fn curl_impersonate_fallback(url: &str, state: &ScrapeState, ctx: &ScraperContext) -> Option<String> {
+    let host = Url::parse(url).ok()?.host_str()?.to_lowercase();
+    let listed = ctx
+        .config
+        .curl_impersonate_domains
+        .iter()
+        .any(|domain| host == *domain || host.ends_with(&format!(".{}", domain)));
+    if !listed {
+        return None;
+    }
+    let body = fetch_with_curl_impersonate(&ctx.config.curl_impersonate_bin, url)?;
+    state.record_bytes(body.len());
+    Some(body)
+}
+
+fn fetch_with_curl_impersonate(bin: &str, url: &str) -> Option<String> {
+    let output = Command::new(bin)
         .arg("-sS")
         .arg(url)
         .output()
         .ok()?;
 
-    if !output.status.success() {
-        println!(
-            "[rust_scraper] curl-impersonate exit_code={} url={}",
-            output.status, url
-        );
-        return None;
+    if !output.status.success() {
+        log::warn!(
+            "curl-impersonate exit_code={} url={}",
+            output.status, url
+        );
+        return None;
+    }
+
+    if looks_binary(&output.stdout) {
+        log::warn!(
+            "curl-impersonate body looks binary ({} bytes), skipping url={}",
+            output.stdout.len(),
+            url
+        );
+        return None;
+    }
+
+    // Mixed-encoding pages often carry a stray invalid byte; keep the rest of the
+    // body instead of discarding it.
+    let stdout = match String::from_utf8_lossy(&output.stdout) {
+        std::borrow::Cow::Borrowed(s) => s.to_string(),
+        std::borrow::Cow::Owned(s) => {
+            log::warn!(
+                "curl-impersonate body had invalid UTF-8, replaced {} chars url={}",
+                s.matches(char::REPLACEMENT_CHARACTER).count(),
+                url
+            );
+            s
+        }
+    };
+    if stdout.is_empty() {
+        log::warn!("curl-impersonate returned empty body url={}", url);
+        return None;
+    }
+
+    log::info!(
+        event = "fetch", source = "curl_impersonate", url = url, bytes = stdout.len() as u64;
+        "curl-impersonate fetched {} bytes url={}",
+        stdout.len(),
+        url
+    );
+    Some(stdout)
+}
+
+/// True when a body is mostly non-printable bytes, e.g. a compressed payload that
+/// never got decoded. Only the first 4KB are sampled.
+fn looks_binary(body: &[u8]) -> bool {
+    let sample = &body[..body.len().min(4096)];
+    if sample.is_empty() {
+        return false;
+    }
+    // gzip magic number
+    if sample.starts_with(&[0x1f, 0x8b]) {
+        return true;
+    }
+    let control = sample
+        .iter()
+        .filter(|&&b| (b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r' | 0x0c)) || b == 0x7f)
+        .count();
+    control * 10 > sample.len()
+}
+
+/// Reads an HTML body, counts it against the scrape's byte total and refuses
+/// bodies that look binary rather than handing them to the parser.
+async fn read_html(resp: wreq::Response, state: &ScrapeState, source: &str) -> Option<String> {
+    if let Some(platform) = platform_from_headers(resp.headers()) {
+        state.page.lock().await.set_platform(platform);
+    }
+    let encoding = resp
+        .headers()
+        .get("content-encoding")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("identity")
+        .to_string();
+    let body = resp.bytes().await.ok()?;
+    state.record_bytes(body.len());
+    if looks_binary(&body) {
+        log::warn!(
+            "{} body looks binary ({} bytes, content-encoding={}), skipping",
+            source,
+            body.len(),
+            encoding
+        );
+        return None;
+    }
+    Some(String::from_utf8_lossy(&body).into_owned())
+}
+
+/// Platform hints some stores leak in response headers.
+fn platform_from_headers(headers: &wreq::header::HeaderMap) -> Option<&'static str> {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_lowercase())
+    };
+    if headers.contains_key("x-shopid") || headers.contains_key("x-shopify-stage") {
+        return Some("shopify");
+    }
+    if header("x-magento-tags").is_some() || header("x-magento-cache-debug").is_some() {
+        return Some("magento");
+    }
+    let powered_by = header("x-powered-by").unwrap_or_default();
+    if powered_by.contains("woocommerce") {
+        Some("woocommerce")
+    } else if powered_by.contains("magento") {
+        Some("magento")
+    } else if powered_by.contains("bigcommerce") {
+        Some("bigcommerce")
+    } else if powered_by.contains("shopify") {
+        Some("shopify")
+    } else {
+        None
+    }
+}
+
+/// Reads a response body as JSON and counts it against the scrape's byte total.
+async fn read_json(resp: wreq::Response, state: &ScrapeState) -> Option<serde_json::Value> {
+    let body = resp.bytes().await.ok()?;
+    state.record_bytes(body.len());
+    serde_json::from_slice(&body).ok()
+}
+
+/// Fixture stand-in for a JSON API call; counted like a real body.
+fn mock_json(fixtures: &Fixtures, kind: &str, key: &str, state: &ScrapeState) -> Option<serde_json::Value> {
+    let json = fixtures.json(kind, key)?;
+    state.record_bytes(json.to_string().len());
+    Some(json)
+}
+
+/// True when a fetch was redirected off the product URL onto a login/account
+/// page that carries no product structured data.
+fn landed_on_login_wall(original_url: &str, final_url: &str, html: &str) -> bool {
+    lazy_static! {
+        static ref LOGIN_PATH_RE: Regex =
+            Regex::new(r"(?i)\b(login|log-in|signin|sign-in|account|auth)\b").unwrap();
+    }
+    let login_path = |u: &str| {
+        Url::parse(u)
+            .map(|parsed| LOGIN_PATH_RE.is_match(parsed.path()))
+            .unwrap_or(false)
+    };
+
+    if normalize_url_path(original_url) == normalize_url_path(final_url)
+        || !login_path(final_url)
+        || login_path(original_url)
+    {
+        return false;
+    }
+
+    let extracted = extract_product_data_from_html(final_url, html);
+    json_ld_products(&extracted).next().is_none() && structured_data_price(&extracted).is_none()
+}
+
+// ==================== HTML EXTRACTION ====================
+
+fn extract_product_data_from_html(url: &str, html: &str) -> serde_json::Value {
+    let extractor = ProductDataExtractor::from_env();
+    extractor.extract_product_data(url, html)
+}
+
+// ==================== DETERMINISTIC EXTRACTION ====================
+
+/// Reads a price from JSON-LD `Product.offers`, a standalone JSON-LD
+/// `Offer`/`AggregateOffer`, microdata `itemprop=price`, or OG/product price
+/// meta tags, in that order.
+fn structured_data_price(extracted: &serde_json::Value) -> Option<Price> {
+    if let Some(price) = json_ld_products(extracted).find_map(json_ld_product_price) {
+        return Some(price);
+    }
+
+    // Some pages ship only an Offer, with no Product around it.
+    let standalone_offer = json_ld_items(extracted)
+        .filter(|item| {
+            matches!(
+                item.get("@type").and_then(|v| v.as_str()),
+                Some("Offer" | "AggregateOffer")
+            )
+        })
+        .find_map(json_ld_offer_price);
+    if standalone_offer.is_some() {
+        return standalone_offer;
+    }
+
+    // Microdata content attributes are machine-readable, so they beat OG tags.
+    if let Some(microdata) = extracted.get("microdata_price").filter(|v| v.is_object()) {
+        let currency = microdata
+            .get("currency")
+            .and_then(|v| v.as_str())
+            .and_then(normalize_currency_code);
+        if let Some(mut price) = microdata.get("price").map(parse_price).filter(|p| p.amount.is_some()) {
+            if currency.is_some() {
+                price.currency = currency;
+            }
+            return Some(price);
+        }
+    }
+
+    let meta = |key: &str| structured_meta(extracted, key);
+    let amount = meta("og:price:amount").or_else(|| meta("product:price:amount"))?;
+    let mut price = parse_price_string(amount);
+    price.amount?;
+    if let Some(currency) = meta("og:price:currency")
+        .or_else(|| meta("product:price:currency"))
+        .and_then(normalize_currency_code)
+    {
+        price.currency = Some(currency);
+    }
+    Some(price)
+}
+
+/// Price of a JSON-LD Product's first priced offer.
+fn json_ld_product_price(product: &serde_json::Value) -> Option<Price> {
+    match product.get("offers")? {
+        serde_json::Value::Array(offers) => offers.iter().find_map(json_ld_offer_price),
+        offer => json_ld_offer_price(offer),
+    }
+}
+
+/// Price of a JSON-LD Offer/AggregateOffer (`price`, `lowPrice` or
+/// `priceSpecification.price`).
+fn json_ld_offer_price(offer: &serde_json::Value) -> Option<Price> {
+    let amount = offer
+        .get("price")
+        .or_else(|| offer.get("lowPrice"))
+        .or_else(|| offer.get("priceSpecification").and_then(|spec| spec.get("price")))?;
+    let currency = offer
+        .get("priceCurrency")
+        .or_else(|| offer.get("priceSpecification").and_then(|spec| spec.get("priceCurrency")))
+        .and_then(|v| v.as_str())
+        .and_then(normalize_currency_code);
+    let mut price = parse_price(amount);
+    price.amount?;
+    if currency.is_some() {
+        price.currency = currency;
+    }
+    Some(price)
+}
+
+/// Named, priced JSON-LD Products when a page lists more than one (bundles,
+/// sets); empty for ordinary single-product pages.
+fn json_ld_bundle_items(extracted: &serde_json::Value) -> Vec<BundleItem> {
+    let items: Vec<BundleItem> = json_ld_products(extracted)
+        .filter_map(|product| {
+            let name = product.get("name")?.as_str()?.trim();
+            if name.is_empty() {
+                return None;
+            }
+            Some(BundleItem {
+                name: name.to_string(),
+                price: json_ld_product_price(product)?,
+            })
+        })
+        .collect();
+    if items.len() < 2 {
+        return Vec::new();
+    }
+    items
+}
+
+/// Sum of the item prices, or None when they mix currencies.
+fn bundle_total(items: &[BundleItem]) -> Option<Price> {
+    let currency = items.first()?.price.currency.clone();
+    if items.iter().any(|item| item.price.currency != currency) {
+        return None;
+    }
+    let cents: i64 = items.iter().map(|item| item.price.amount_cents).sum::<Option<i64>>()?;
+    Some(Price {
+        amount: Some((cents / 100) as i32),
+        amount_cents: Some(cents),
+        currency,
+    })
+}
+
+/// Last-resort price from the first of the extractor's `price_signals` (the
+/// price-classed elements come first); several amounts in it ("Now $25 Was
+/// $50") resolve to the current one.
+fn price_signal_price(extracted: &serde_json::Value) -> Option<Price> {
+    let signal = extracted.get("price_signals")?.as_array()?.first()?.as_str()?;
+    select_current_price(&price_candidates(signal))
+}
+
+/// (current, original) prices from the extractor's `<ins>`/`<del>` sale markup.
+/// Ignored unless the struck-out price is actually higher.
+fn sale_markup_prices(extracted: &serde_json::Value) -> Option<(Price, Price)> {
+    let sale = extracted.get("sale_markup")?;
+    let current = parse_price_string(sale.get("price")?.as_str()?);
+    let mut original = parse_price_string(sale.get("original_price")?.as_str()?);
+    if original.amount_cents? <= current.amount_cents? {
+        return None;
+    }
+    if original.currency.is_none() {
+        original.currency = current.currency.clone();
+    }
+    Some((current, original))
+}
+
+/// og:type when it names a non-product page ("article", "website", ...) and the
+/// page has no Product JSON-LD or price tags to contradict it. Pages without
+/// og:type give no signal.
+fn non_product_og_type(extracted: &serde_json::Value) -> Option<&str> {
+    let og_type = extracted.get("og_type").and_then(|v| v.as_str())?;
+    if og_type.contains("product") {
+        return None;
+    }
+    if json_ld_products(extracted).next().is_some() || structured_data_price(extracted).is_some() {
+        return None;
+    }
+    Some(og_type)
+}
+
+/// Sizes from JSON-LD (`size` on the product, its offers or ProductGroup
+/// `hasVariant`s), falling back to the extractor's size `<select>` options.
+fn structured_sizes(extracted: &serde_json::Value) -> Vec<String> {
+    fn as_list(value: Option<&serde_json::Value>) -> Vec<&serde_json::Value> {
+        match value {
+            Some(serde_json::Value::Array(items)) => items.iter().collect(),
+            Some(other) => vec![other],
+            None => Vec::new(),
+        }
+    }
+
+    fn size_of(item: &serde_json::Value) -> Option<&str> {
+        let size = item.get("size")?;
+        size.as_str().or_else(|| size.get("name").and_then(|v| v.as_str()))
+    }
+
+    // Offers without a `size` often end their SKU in an alpha size ("TEE-BLU-M").
+    fn sku_size(item: &serde_json::Value) -> Option<&str> {
+        lazy_static! {
+            static ref SKU_SIZE_RE: Regex = Regex::new(r"(?i)^(xxs|xs|s|m|l|xl|xxl|xxxl|[2-5]xl)$").unwrap();
+        }
+        let sku = item.get("sku")?.as_str()?;
+        sku.rsplit(['-', '_', '/']).next().filter(|token| SKU_SIZE_RE.is_match(token))
+    }
+
+    let mut raw: Vec<&str> = Vec::new();
+    for product in json_ld_products(extracted) {
+        raw.extend(size_of(product));
+        let variants = as_list(product.get("offers"))
+            .into_iter()
+            .chain(as_list(product.get("hasVariant")));
+        raw.extend(variants.filter_map(|item| size_of(item).or_else(|| sku_size(item))));
+    }
+    if raw.is_empty() {
+        raw.extend(
+            extracted
+                .get("sizes")
+                .and_then(|v| v.as_array())
+                .into_iter()
+                .flatten()
+                .filter_map(|v| v.as_str()),
+        );
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    raw.into_iter()
+        .map(str::trim)
+        .filter(|size| !size.is_empty() && seen.insert(size.to_lowercase()))
+        .map(String::from)
+        .collect()
+}
+
+/// Colors from JSON-LD (`color` on the product, its offers or ProductGroup
+/// `hasVariant`s), falling back to the extractor's color swatches.
+fn structured_colors(extracted: &serde_json::Value) -> Vec<String> {
+    let color_of = |item: &serde_json::Value| item.get("color").and_then(|v| v.as_str());
+    let mut raw: Vec<&str> = Vec::new();
+    for product in json_ld_products(extracted) {
+        raw.extend(color_of(product));
+        for key in ["offers", "hasVariant"] {
+            match product.get(key) {
+                Some(serde_json::Value::Array(items)) => raw.extend(items.iter().filter_map(color_of)),
+                Some(other) => raw.extend(color_of(other)),
+                None => {}
+            }
+        }
+    }
+    if raw.is_empty() {
+        raw.extend(
+            extracted
+                .pointer("/color_swatches/colors")
+                .and_then(|v| v.as_array())
+                .into_iter()
+                .flatten()
+                .filter_map(|v| v.as_str()),
+        );
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    raw.into_iter()
+        .map(str::trim)
+        .filter(|color| !color.is_empty() && seen.insert(color.to_lowercase()))
+        .map(String::from)
+        .collect()
+}
+
+/// Best-effort mapping of a size token to a canonical notation: alpha sizes to
+/// XXS..XXXL ("Medium" -> "M", "x-large" -> "XL", "2XL" -> "XXL") and regional
+/// numeric sizes to "EU 38" / "US 8" / "UK 6". Unrecognized tokens come back
+/// trimmed but otherwise unchanged.
+fn normalize_size(raw: &str) -> String {
+    let trimmed = raw.trim();
+    let lower = trimmed.to_lowercase();
+    let token = lower.strip_prefix("size").map(str::trim).unwrap_or(&lower);
+    let compact: String = token.chars().filter(|c| !matches!(c, ' ' | '-' | '_')).collect();
+
+    let alpha = match compact.as_str() {
+        "xxs" | "extraextrasmall" | "xxsmall" | "2xs" => Some("XXS"),
+        "xs" | "extrasmall" | "xsmall" => Some("XS"),
+        "s" | "small" | "sm" => Some("S"),
+        "m" | "medium" | "med" => Some("M"),
+        "l" | "large" | "lg" => Some("L"),
+        "xl" | "extralarge" | "xlarge" => Some("XL"),
+        "xxl" | "extraextralarge" | "xxlarge" | "2xl" => Some("XXL"),
+        "xxxl" | "xxxlarge" | "3xl" => Some("XXXL"),
+        _ => None,
+    };
+    if let Some(alpha) = alpha {
+        return alpha.to_string();
+    }
+
+    lazy_static! {
+        static ref REGIONAL_SIZE_RE: Regex =
+            Regex::new(r"^(?:(eu|us|uk|it|fr)\s*(\d{1,2}(?:[.,]5)?)|(\d{1,2}(?:[.,]5)?)\s*(eu|us|uk|it|fr))$").unwrap();
+    }
+    if let Some(caps) = REGIONAL_SIZE_RE.captures(token) {
+        let region = caps.get(1).or_else(|| caps.get(4)).map(|m| m.as_str().to_uppercase());
+        let number = caps.get(2).or_else(|| caps.get(3)).map(|m| m.as_str().replace(',', "."));
+        if let (Some(region), Some(number)) = (region, number) {
+            return format!("{} {}", region, number);
+        }
+    }
+    trimmed.to_string()
+}
+
+/// Every JSON-LD object the extractor kept (products and offers).
+fn json_ld_items(extracted: &serde_json::Value) -> impl Iterator<Item = &serde_json::Value> {
+    extracted
+        .pointer("/structured_data/json_ld")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+}
+
+/// JSON-LD Product/ProductGroup objects from the extractor output.
+fn json_ld_products(extracted: &serde_json::Value) -> impl Iterator<Item = &serde_json::Value> {
+    json_ld_items(extracted)
+        .filter(|item| {
+            matches!(
+                item.get("@type").and_then(|v| v.as_str()),
+                Some("Product" | "ProductGroup")
+            )
+        })
+}
+
+/// True when the page exposes machine-readable product data rather than only
+/// markup for the heuristics: JSON-LD products, microdata, RDFa, or an OG
+/// title together with a price tag.
+fn has_structured_data(extracted: &serde_json::Value) -> bool {
+    let non_empty = |pointer: &str| match extracted.pointer(pointer) {
+        Some(serde_json::Value::Array(items)) => !items.is_empty(),
+        Some(serde_json::Value::Object(map)) => !map.is_empty(),
+        _ => false,
+    };
+    let og_product = structured_meta(extracted, "og:title").is_some()
+        && (structured_meta(extracted, "og:price:amount").is_some()
+            || structured_meta(extracted, "product:price:amount").is_some());
+    non_empty("/structured_data/json_ld")
+        || non_empty("/structured_data/microdata")
+        || non_empty("/structured_data/rdfa")
+        || og_product
+}
+
+/// Looks up an OG tag, falling back to the product/price meta tags.
+fn structured_meta<'a>(extracted: &'a serde_json::Value, key: &str) -> Option<&'a str> {
+    let structured = extracted.get("structured_data")?;
+    structured
+        .get("open_graph")
+        .and_then(|og| og.get(key))
+        .or_else(|| structured.get("meta_tags").and_then(|m| m.get(key)))
+        .and_then(|v| v.as_str())
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+}
+
+fn normalize_availability(raw: &str) -> Option<&'static str> {
+    // schema.org URLs ("https://schema.org/InStock") and OG values ("instock", "oos")
+    let value = raw
+        .rsplit('/')
+        .next()
+        .unwrap_or(raw)
+        .to_lowercase()
+        .replace([' ', '_', '-'], "");
+    match value.as_str() {
+        "instock" | "onlineonly" | "instoreonly" | "preorder" | "presale" => Some("in_stock"),
+        "outofstock" | "soldout" | "discontinued" | "oos" => Some("out_of_stock"),
+        "limitedavailability" | "limited" => Some("limited"),
+        _ => None,
+    }
+}
+
+/// Image URLs from a string, an array of strings, or an array of `{src|url}`
+/// objects, minus data URIs and icons/logos. GIFs follow `keep_gifs_if_needed`.
+fn usable_image_urls(value: Option<&serde_json::Value>) -> Vec<serde_json::Value> {
+    let items: Vec<&serde_json::Value> = match value {
+        Some(serde_json::Value::Array(items)) => items.iter().collect(),
+        Some(single) => vec![single],
+        None => Vec::new(),
+    };
+    let urls: Vec<&str> = items
+        .into_iter()
+        .filter_map(|img| {
+            img.as_str()
+                .or_else(|| img.get("src").and_then(|v| v.as_str()))
+                .or_else(|| img.get("url").and_then(|v| v.as_str()))
+        })
+        .filter(|src| {
+            let lower = src.to_lowercase();
+            lower.starts_with("http") && !["favicon", "icon", "logo"].iter().any(|p| lower.contains(p))
+        })
+        .collect();
+    keep_gifs_if_needed(urls, |src: &&str| *src)
+        .into_iter()
+        .map(|src| serde_json::Value::String(src.to_string()))
+        .collect()
+}
+
+lazy_static! {
+    /// ALLOW_GIF_IMAGES=1 keeps GIFs alongside other images.
+    static ref ALLOW_GIF_IMAGES: bool = matches!(
+        env_var("ALLOW_GIF_IMAGES").as_deref(),
+        Some("1" | "true" | "yes")
+    );
+}
+
+/// Drops GIFs from an image list unless ALLOW_GIF_IMAGES is set or GIFs are the
+/// only candidates, so GIF-only pages (animated spins) never end up empty.
+fn keep_gifs_if_needed<T>(images: Vec<T>, url: impl Fn(&T) -> &str) -> Vec<T> {
+    let is_gif = |img: &T| {
+        let lower = url(img).to_lowercase();
+        lower.split(['?', '#']).next().unwrap_or("").ends_with(".gif")
+    };
+    if *ALLOW_GIF_IMAGES || images.iter().all(is_gif) {
+        return images;
+    }
+    images.into_iter().filter(|img| !is_gif(img)).collect()
+}
+
+/// Fields derived from the extractor output without any LLM call.
+fn structured_fields(extracted: &serde_json::Value) -> HashMap<String, serde_json::Value> {
+    let mut fields = HashMap::new();
+
+    let product = json_ld_products(extracted).next();
+
+    let name = product
+        .and_then(|p| p.get("name"))
+        .and_then(|v| v.as_str())
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .or_else(|| structured_meta(extracted, "og:title"));
+    if let Some(name) = name {
+        fields.insert("product_name".to_string(), serde_json::Value::String(name.to_string()));
+    }
+
+    let brand = product
+        .and_then(|p| p.get("brand"))
+        .and_then(|b| b.as_str().or_else(|| b.get("name").and_then(|v| v.as_str())))
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .or_else(|| structured_meta(extracted, "product:brand"))
+        .or_else(|| structured_meta(extracted, "og:brand"));
+    if let Some(brand) = brand {
+        fields.insert("brand".to_string(), serde_json::Value::String(brand.to_string()));
+    }
+
+    if let Some(price) = structured_data_price(extracted) {
+        fields.insert(
+            "price".to_string(),
+            serde_json::json!({ "amount": price.amount, "amount_cents": price.amount_cents, "currency": price.currency }),
+        );
+    }
+
+    // <del>/<ins> sale markup: the struck-out amount is the original price and
+    // fills in the current price when structured data had none.
+    if let Some((current, original)) = sale_markup_prices(extracted) {
+        if !fields.contains_key("price") {
+            fields.insert(
+                "price".to_string(),
+                serde_json::json!({ "amount": current.amount, "amount_cents": current.amount_cents, "currency": current.currency }),
+            );
+            if let Some(raw) = extracted.pointer("/sale_markup/price").and_then(|v| v.as_str()) {
+                fields.insert("price_raw".to_string(), serde_json::Value::String(raw.to_string()));
+            }
+        }
+        fields.insert(
+            "original_price".to_string(),
+            serde_json::json!({ "amount": original.amount, "amount_cents": original.amount_cents, "currency": original.currency }),
+        );
+        fields.insert("on_sale".to_string(), serde_json::Value::Bool(true));
+    }
+    if !fields.contains_key("price") {
+        if let Some(price) = price_signal_price(extracted) {
+            fields.insert(
+                "price".to_string(),
+                serde_json::json!({ "amount": price.amount, "amount_cents": price.amount_cents, "currency": price.currency }),
+            );
+            if let Some(raw) = extracted.pointer("/price_signals/0").and_then(|v| v.as_str()) {
+                fields.insert("price_raw".to_string(), serde_json::Value::String(raw.to_string()));
+            }
+        }
+    }
+
+    // Same exclusions the Gemini prompt applies to the extractor's image list,
+    // with og:image (a string, or an array when pre-parsed) as the fallback.
+    let mut images = usable_image_urls(extracted.get("images"));
+    if images.is_empty() {
+        let og_image = extracted
+            .pointer("/structured_data/open_graph/og:image")
+            .or_else(|| extracted.get("og:image"));
+        images = usable_image_urls(og_image);
+    }
+    if !images.is_empty() {
+        fields.insert("image_urls".to_string(), serde_json::Value::Array(images));
+    }
+
+    let availability = product
+        .and_then(|p| p.get("offers"))
+        .and_then(|offers| match offers {
+            serde_json::Value::Array(items) => items.first(),
+            other => Some(other),
+        })
+        .and_then(|offer| offer.get("availability"))
+        .and_then(|v| v.as_str())
+        .or_else(|| structured_meta(extracted, "og:availability"))
+        .or_else(|| structured_meta(extracted, "product:availability"))
+        .and_then(normalize_availability);
+    if let Some(availability) = availability {
+        fields.insert("availability".to_string(), serde_json::Value::String(availability.to_string()));
+    }
+
+    let color = product
+        .and_then(|p| p.get("color"))
+        .and_then(|v| v.as_str())
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .or_else(|| structured_meta(extracted, "product:color"))
+        .or_else(|| structured_meta(extracted, "og:product:color"))
+        .or_else(|| {
+            extracted
+                .pointer("/structured_data/microdata/color")
+                .and_then(|v| v.as_str())
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+        });
+    if let Some(color) = color {
+        fields.insert("color".to_string(), serde_json::Value::String(color.to_string()));
+    }
+
+    // The active swatch names the displayed variant; a JSON-LD product color
+    // describes the page's own product.
+    let selected_color = extracted
+        .pointer("/color_swatches/selected")
+        .and_then(|v| v.as_str())
+        .or_else(|| product.and_then(|p| p.get("color")).and_then(|v| v.as_str()))
+        .map(str::trim)
+        .filter(|s| !s.is_empty());
+    if let Some(selected_color) = selected_color {
+        fields.insert("selected_color".to_string(), serde_json::Value::String(selected_color.to_string()));
+    }
+
+    let release_date = product
+        .and_then(|p| p.get("releaseDate").or_else(|| p.get("datePublished")))
+        .and_then(|v| v.as_str())
+        .map(str::trim)
+        .filter(|s| !s.is_empty());
+    if let Some(release_date) = release_date {
+        fields.insert("release_date".to_string(), serde_json::Value::String(release_date.to_string()));
+    }
+
+    if extracted.get("price_gated").and_then(|v| v.as_bool()) == Some(true) {
+        fields.insert("price_gated".to_string(), serde_json::Value::Bool(true));
+    }
+
+    let shipping_info = product
+        .and_then(json_ld_shipping_info)
+        .or_else(|| extracted.get("shipping_info").and_then(|v| v.as_str()).map(str::to_string));
+    if let Some(shipping_info) = shipping_info {
+        fields.insert("shipping_info".to_string(), serde_json::Value::String(shipping_info));
+    }
+
+    let specifications: Vec<String> = extracted
+        .pointer("/content/specifications")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|v| v.as_str().map(String::from))
+        .collect();
+    if let Some(fit) = fit_descriptor(name, &specifications) {
+        fields.insert("fit".to_string(), serde_json::Value::String(fit.to_string()));
+    }
+
+    let bundle_items = json_ld_bundle_items(extracted);
+    if !bundle_items.is_empty() {
+        if let Ok(items) = serde_json::to_value(&bundle_items) {
+            fields.insert("bundle_items".to_string(), items);
+        }
+    }
+
+    let sizes = structured_sizes(extracted);
+    if !sizes.is_empty() {
+        fields.insert(
+            "sizes".to_string(),
+            serde_json::Value::Array(sizes.into_iter().map(serde_json::Value::String).collect()),
+        );
+    }
+
+    let colors = structured_colors(extracted);
+    if !colors.is_empty() {
+        fields.insert(
+            "colors".to_string(),
+            serde_json::Value::Array(colors.into_iter().map(serde_json::Value::String).collect()),
+        );
+    }
+
+    if let Some(size_chart) = extracted.get("size_chart").filter(|v| v.is_object()) {
+        fields.insert("size_chart".to_string(), size_chart.clone());
+    }
+
+    fields
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GarmentType {
+    Upper,
+    Lower,
+    FullBody,
+    Shoes,
+    Other,
+}
+
+impl GarmentType {
+    fn as_str(self) -> &'static str {
+        match self {
+            GarmentType::Upper => "upper",
+            GarmentType::Lower => "lower",
+            GarmentType::FullBody => "full_body",
+            GarmentType::Shoes => "shoes",
+            GarmentType::Other => "other",
+        }
+    }
+}
+
+/// Keyword lists for `classify_garment_heuristic`, checked in this order when a
+/// single token could match more than one type. Plurals ("-s", "-es") match too.
+const GARMENT_KEYWORDS: &[(GarmentType, &[&str])] = &[
+    (
+        GarmentType::FullBody,
+        &["dress", "jumpsuit", "romper", "playsuit", "overall", "gown", "bodysuit", "onesie"],
+    ),
+    (
+        GarmentType::Shoes,
+        &["sneaker", "boot", "sandal", "loafer", "heel", "shoe", "trainer", "mule", "pump", "slipper", "espadrille"],
+    ),
+    (
+        GarmentType::Lower,
+        &["pant", "trouser", "jean", "short", "skirt", "legging", "jogger", "chino", "culotte"],
+    ),
+    (
+        GarmentType::Upper,
+        &[
+            "shirt", "tshirt", "tee", "top", "blouse", "sweater", "hoodie", "jacket", "coat", "cardigan",
+            "blazer", "vest", "jumper", "sweatshirt", "polo", "parka", "pullover", "tank", "camisole",
+        ],
+    ),
+    (
+        GarmentType::Other,
+        &[
+            "bag", "handbag", "tote", "backpack", "hat", "cap", "beanie", "belt", "scarf", "wallet",
+            "necklace", "earring", "bracelet", "ring", "sunglasses", "watch", "jewelry", "jewellery",
+        ],
+    ),
+];
+
+/// Keyword-based garment_type guess, used as the lowest-priority source so the
+/// result doesn't depend on an LLM. Checks the product name, then the URL path,
+/// then breadcrumbs; each text is scanned from the end since the head noun usually
+/// comes last ("short sleeve shirt", "shirt dress").
+fn classify_garment_heuristic(
+    name: Option<&str>,
+    url: &str,
+    breadcrumbs: &[String],
+) -> Option<GarmentType> {
+    let path = Url::parse(url).ok().map(|u| u.path().to_string()).unwrap_or_default();
+    name.into_iter()
+        .chain(std::iter::once(path.as_str()))
+        .chain(breadcrumbs.iter().map(String::as_str))
+        .find_map(garment_keyword_match)
+        .map(|(gtype, _)| gtype)
+}
+
+/// Last token in `text` matching a garment keyword (plurals included), with the
+/// token as written, e.g. (FullBody, "dresses").
+fn garment_keyword_match(text: &str) -> Option<(GarmentType, String)> {
+    let lower = text.to_lowercase();
+    let tokens: Vec<&str> = lower
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .collect();
+    tokens.iter().rev().find_map(|token| {
+        GARMENT_KEYWORDS.iter().find_map(|(gtype, words)| {
+            words
+                .iter()
+                .any(|w| {
+                    *token == *w
+                        || token.strip_suffix('s') == Some(*w)
+                        || token.strip_suffix("es") == Some(*w)
+                })
+                .then(|| (*gtype, token.to_string()))
+        })
+    })
+}
+
+/// Gender implied by the URL path ("/women/", "/mens-shoes/", "/unisex/").
+fn url_gender_hint(url: &str) -> Option<&'static str> {
+    if is_unisex(url, &[]) {
+        return Some("unisex");
+    }
+    let path = Url::parse(url).ok()?.path().to_lowercase();
+    let tokens: Vec<&str> = path.split(|c: char| !c.is_alphanumeric()).collect();
+    if tokens.iter().any(|t| matches!(*t, "women" | "womens" | "woman" | "ladies" | "female")) {
+        Some("female")
+    } else if tokens.iter().any(|t| matches!(*t, "men" | "mens" | "man" | "male")) {
+        Some("male")
+    } else {
+        None
+    }
+}
+
+/// Labeled hints for the extraction prompt, computed from the URL and breadcrumbs
+/// so Gemini gets explicit priors instead of having to spot them in raw data.
+fn prompt_hints(url: &str, extracted: &serde_json::Value) -> Vec<String> {
+    let mut hints = Vec::new();
+    if let Some(gender) = url_gender_hint(url) {
+        hints.push(format!("URL_GENDER_HINT: {}", gender));
+    }
+    let breadcrumbs = extracted_breadcrumbs(extracted);
+    if let Some((_, term)) = breadcrumbs.iter().rev().find_map(|crumb| garment_keyword_match(crumb)) {
+        hints.push(format!("BREADCRUMB_TYPE_HINT: {}", term));
+    }
+    hints
+}
+
+fn extracted_breadcrumbs(extracted: &serde_json::Value) -> Vec<String> {
+    extracted
+        .get("breadcrumbs")
+        .or_else(|| extracted.pointer("/content/breadcrumbs"))
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|v| v.as_str().map(String::from))
+        // The dataLayer category ("Apparel/Women/Dresses") reads like a trail.
+        .chain(extracted.pointer("/data_layer/category").and_then(|v| v.as_str()).map(String::from))
+        .collect()
+}
+
+fn garment_heuristic_fields(
+    name: Option<&str>,
+    url: &str,
+    breadcrumbs: &[String],
+) -> HashMap<String, serde_json::Value> {
+    let mut fields = HashMap::new();
+    if let Some(gtype) = classify_garment_heuristic(name, url, breadcrumbs) {
+        fields.insert(
+            "garment_type".to_string(),
+            serde_json::Value::String(gtype.as_str().to_string()),
+        );
+    }
+    if is_unisex(url, breadcrumbs) {
+        fields.insert("gender".to_string(), serde_json::Value::String("unisex".to_string()));
+    }
+    fields
+}
+
+/// Name/price/images the extractor pulled from an embedded Apollo/Relay cache.
+fn app_state_fields(extracted: &serde_json::Value) -> HashMap<String, serde_json::Value> {
+    let mut fields = HashMap::new();
+    let Some(app_state) = extracted.get("app_state").filter(|v| v.is_object()) else {
+        return fields;
+    };
+
+    if let Some(name) = app_state.get("name").and_then(|v| v.as_str()) {
+        fields.insert("product_name".to_string(), serde_json::Value::String(name.to_string()));
+    }
+
+    if let Some(mut price) = app_state.get("price").map(parse_price).filter(|p| p.amount.is_some()) {
+        if let Some(currency) = app_state
+            .get("currency")
+            .and_then(|v| v.as_str())
+            .and_then(normalize_currency_code)
+        {
+            price.currency = Some(currency);
+        }
+        fields.insert(
+            "price".to_string(),
+            serde_json::json!({ "amount": price.amount, "amount_cents": price.amount_cents, "currency": price.currency }),
+        );
+    }
+
+    let images = usable_image_urls(app_state.get("images"));
+    if !images.is_empty() {
+        fields.insert("image_urls".to_string(), serde_json::Value::Array(images));
+    }
+
+    fields
+}
+
+/// Deterministic fields from the page's GTM `dataLayer` ecommerce push. The
+/// category and product id stay in the extractor output for the prompt.
+fn data_layer_fields(extracted: &serde_json::Value) -> HashMap<String, serde_json::Value> {
+    let mut fields = HashMap::new();
+    let Some(data_layer) = extracted.get("data_layer").filter(|v| v.is_object()) else {
+        return fields;
+    };
+
+    for (key, field) in [("name", "product_name"), ("brand", "brand")] {
+        if let Some(value) = data_layer.get(key).and_then(|v| v.as_str()) {
+            fields.insert(field.to_string(), serde_json::Value::String(value.to_string()));
+        }
+    }
+
+    if let Some(mut price) = data_layer.get("price").map(parse_price).filter(|p| p.amount.is_some()) {
+        if let Some(currency) = data_layer
+            .get("currency")
+            .and_then(|v| v.as_str())
+            .and_then(normalize_currency_code)
+        {
+            price.currency = Some(currency);
+        }
+        fields.insert(
+            "price".to_string(),
+            serde_json::json!({ "amount": price.amount, "amount_cents": price.amount_cents, "currency": price.currency }),
+        );
+    }
+
+    fields
+}
+
+/// Summarizes JSON-LD `offers.shippingDetails` (rate + handling/transit days),
+/// e.g. "Shipping: 5.99 USD; delivery in 2-5 days".
+fn json_ld_shipping_info(product: &serde_json::Value) -> Option<String> {
+    let offer = match product.get("offers")? {
+        serde_json::Value::Array(items) => items.first()?,
+        other => other,
+    };
+    let details = match offer.get("shippingDetails")? {
+        serde_json::Value::Array(items) => items.first()?,
+        other => other,
+    };
+    let text = |v: &serde_json::Value| match v {
+        serde_json::Value::String(s) => Some(s.trim().to_string()),
+        serde_json::Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    };
+
+    let mut parts = Vec::new();
+    if let Some(rate) = details.get("shippingRate") {
+        let value = rate.get("value").and_then(text);
+        let currency = rate.get("currency").and_then(text).unwrap_or_default();
+        match value.as_deref() {
+            Some("0") | Some("0.0") | Some("0.00") => parts.push("Free shipping".to_string()),
+            Some(value) => parts.push(format!("Shipping: {} {}", value, currency).trim().to_string()),
+            None => {}
+        }
+    }
+    if let Some(time) = details.get("deliveryTime") {
+        let days = |key: &str| -> (i64, i64) {
+            let range = time.get(key);
+            let bound = |k: &str| {
+                range
+                    .and_then(|r| r.get(k))
+                    .and_then(|v| v.as_i64().or_else(|| v.as_str().and_then(|s| s.parse().ok())))
+                    .unwrap_or(0)
+            };
+            (bound("minValue"), bound("maxValue"))
+        };
+        let (handling_min, handling_max) = days("handlingTime");
+        let (transit_min, transit_max) = days("transitTime");
+        let (min, max) = (handling_min + transit_min, handling_max + transit_max);
+        if max > 0 {
+            parts.push(if min > 0 && min < max {
+                format!("delivery in {}-{} days", min, max)
+            } else {
+                format!("delivery in {} days", max)
+            });
+        }
+    }
+
+    (!parts.is_empty()).then(|| parts.join("; "))
+}
+
+/// Canonical fit → regex alternation of its spellings. Ambiguous words ("slim",
+/// "regular", "straight") only count as a fit next to "fit" in a product name.
+const FIT_VOCABULARY: &[(&str, &str, bool)] = &[
+    // (canonical, pattern, unambiguous on its own)
+    ("oversized", r"over-?sized?", true),
+    ("skinny", r"(?:super[- ]?)?skinny", true),
+    ("boxy", r"boxy", true),
+    ("relaxed", r"relaxed", false),
+    ("loose", r"loose", false),
+    ("slim", r"(?:extra[- ]?)?slim", false),
+    ("tapered", r"tapered", false),
+    ("straight", r"straight", false),
+    ("athletic", r"athletic", false),
+    ("regular", r"regular|classic", false),
+];
+
+/// Fit from the product name ("Slim Fit Oxford Shirt", "Oversized Tee"), else
+/// from a specification row that talks about fit ("Fit: Relaxed").
+fn fit_descriptor(name: Option<&str>, specifications: &[String]) -> Option<&'static str> {
+    lazy_static! {
+        /// (canonical, name matcher, spec-row matcher) per FIT_VOCABULARY entry.
+        static ref FIT_RES: Vec<(&'static str, Regex, Regex)> = FIT_VOCABULARY
+            .iter()
+            .map(|(fit, pattern, standalone)| {
+                let in_name = if *standalone {
+                    format!(r"(?i)\b(?:{})\b", pattern)
+                } else {
+                    format!(r"(?i)\b(?:{})[\s-]*fit(?:ting)?\b", pattern)
+                };
+                let in_spec = format!(r"(?i)\b(?:{})\b", pattern);
+                (*fit, Regex::new(&in_name).unwrap(), Regex::new(&in_spec).unwrap())
+            })
+            .collect();
+        static ref FIT_ROW_RE: Regex = Regex::new(r"(?i)\bfit").unwrap();
     }
+    let in_name = |name: &str| {
+        FIT_RES
+            .iter()
+            .find_map(|(fit, name_re, _)| name_re.is_match(name).then_some(*fit))
+    };
+    let in_spec = |row: &String| {
+        if !FIT_ROW_RE.is_match(row) {
+            return None;
+        }
+        FIT_RES
+            .iter()
+            .find_map(|(fit, _, spec_re)| spec_re.is_match(row).then_some(*fit))
+    };
+    name.and_then(in_name)
+        .or_else(|| specifications.iter().find_map(in_spec))
+}
 
-    let stdout = String::from_utf8(output.stdout).ok()?;
-    if stdout.is_empty() {
-        println!("[rust_scraper] curl-impersonate returned empty body url={}", url);
-        return None;
+/// Locale in one of the first two URL path segments ("/en-us/", "/fr_FR/"),
+/// normalized like the extractor's `<html lang>`.
+fn url_locale(url: &str) -> Option<String> {
+    lazy_static! {
+        static ref LOCALE_SEGMENT_RE: Regex = Regex::new(r"^[A-Za-z]{2}[-_][A-Za-z]{2}$").unwrap();
     }
+    let parsed = Url::parse(url).ok()?;
+    let segment = parsed
+        .path_segments()?
+        .take(2)
+        .find(|segment| LOCALE_SEGMENT_RE.is_match(segment))?;
+    html_extractor::normalize_locale(segment)
+}
 
-    println!(
-        "[rust_scraper] curl-impersonate fetched {} bytes url={}",
-        stdout.len(),
-        url
-    );
-    Some(stdout)
+/// True when the URL path or breadcrumbs explicitly mark the product as unisex.
+fn is_unisex(url: &str, breadcrumbs: &[String]) -> bool {
+    lazy_static! {
+        static ref UNISEX_RE: Regex = Regex::new(r"(?i)\b(unisex|gender[-_ ]?neutral|all[-_ ]gender)\b").unwrap();
+    }
+    let path = Url::parse(url).map(|u| u.path().to_string()).unwrap_or_default();
+    UNISEX_RE.is_match(&path) || breadcrumbs.iter().any(|crumb| UNISEX_RE.is_match(crumb))
 }
 
-// ==================== HTML EXTRACTION ====================
+/// Deterministic fields from the Cloudflare worker response. The worker may return
+/// extractor-shaped data or flat, already-parsed fields (`images`/`image_urls`,
+/// `og:image` arrays, `price`, `brand`, `name`/`title`); handle both.
+fn worker_fields(data: &serde_json::Value) -> HashMap<String, serde_json::Value> {
+    let mut fields = structured_fields(data);
 
-fn extract_product_data_from_html(url: &str, html: &str) -> serde_json::Value {
-    let extractor = ProductDataExtractor::new(50_000);
-    extractor.extract_product_data(url, html)
+    if !fields.contains_key("image_urls") {
+        let images = usable_image_urls(data.get("image_urls"));
+        if !images.is_empty() {
+            fields.insert("image_urls".to_string(), serde_json::Value::Array(images));
+        }
+    }
+    if !fields.contains_key("product_name") {
+        if let Some(name) = ["product_name", "name", "title"]
+            .iter()
+            .find_map(|key| data.get(*key).and_then(|v| v.as_str()))
+            .filter(|s| !s.trim().is_empty())
+        {
+            fields.insert("product_name".to_string(), serde_json::Value::String(name.trim().to_string()));
+        }
+    }
+    if !fields.contains_key("brand") {
+        if let Some(brand) = data.get("brand").and_then(|v| v.as_str()).filter(|s| !s.trim().is_empty()) {
+            fields.insert("brand".to_string(), serde_json::Value::String(brand.trim().to_string()));
+        }
+    }
+    if !fields.contains_key("price") {
+        if let Some(price) = data.get("price").filter(|v| !v.is_null()) {
+            fields.insert("price".to_string(), price.clone());
+        }
+    }
+
+    fields
 }
 
 // ==================== GEMINI CLIENT ====================
@@ -438,13 +3097,23 @@ fn extract_product_data_from_html(url: &str, html: &str) -> serde_json::Value {
 async fn call_gemini_for_product_extraction(
     url_for_log: &str,
     extracted_data: &serde_json::Value,
-    client: &wreq::Client,
+    state: &ScrapeState,
+    ctx: &ScraperContext,
 ) -> Option<HashMap<String, serde_json::Value>> {
-    let genai_key = env_var("GENAI_API_KEY")?;
-    let url = format!(
-        "https://generativelanguage.googleapis.com/v1beta/models/gemini-flash-lite-latest:generateContent?key={}",
-        genai_key
-    );
+    let url = ctx.gemini_url(&ctx.config.gemini_model)?;
+    if state.byte_budget_exhausted("gemini extraction") {
+        return None;
+    }
+
+    let hints = prompt_hints(url_for_log, extracted_data);
+    let hints_block = if hints.is_empty() {
+        String::new()
+    } else {
+        format!(
+            "DETERMINISTIC HINTS (derived from the URL and breadcrumbs; treat as strong priors unless the data clearly contradicts them):\n{}\n\n",
+            hints.join("\n")
+        )
+    };
 
     let prompt = format!(
         r#"
@@ -462,28 +3131,33 @@ YOUR TASK:
      * PRIORITY: Look in JSON-LD/structured_data first (offers.price, og:price:amount) and fallback to price_signals array and use the below logic.
      * If you see multiple prices (e.g., "Now $25.00+" and "Original Price: $50.00+"), return the LOWER price (the current/sale price)
      * If only a price range exists (e.g., "$25-$50"), return the lower bound
+     * "variant_prices" pairs a size/variant label with its price; when prices differ by variant, return the lowest one
      * Return empty string if no valid price found
    - garment_type: Classify the clothing type. "upper" for tops/outerwear (shirts, jackets, etc.), "lower" for bottoms (pants, shorts, skirts, etc.), "full_body" for anything that would be a full outfit, like dresses, loungewear, pajamas, full body suits, etc. , "shoes" for footwear, "other" for accessories (bags, hats, jewelry), "unsupported" for non-clothing items (e.g. toys, furniture, electronics, etc.)
-   - gender: Infer the target gender for this product. Return "male" for menswear, "female" for womenswear. Look for keywords in product name, category, URL, or structured data (e.g., "men's", "women's", "ladies", "mens"). 
+   - gender: Infer the target gender for this product. Return "male" for menswear, "female" for womenswear, "unisex" for products explicitly sold as unisex/gender-neutral, "kids" for children's/baby clothing. Look for keywords in product name, category, URL, or structured data (e.g., "men's", "women's", "ladies", "mens"). 
    - image_urls: Extract EVERY valid product image URL from the data. CRITICAL INSTRUCTIONS:
      * If "images" array exists: Include EVERY URL from it (all angles, all colors, all variants)
-     * Skip URLs containing "data:image/", "favicon", "icon", "logo", or ending with ".gif" - basically whatever doesn't feel like a product image
+     * Skip URLs containing "data:image/", "favicon", "icon", "logo", or ending with ".gif" (unless GIFs are the only product images) - basically whatever doesn't feel like a product image
      * If "images" array is empty/missing: Use "structured_data.open_graph.og:image" as fallback (only if it's a valid http/https URL)
      * NEVER limit the number of images - if there are 10 images, return all 10. If there are 20 images, return all 20
      * Only return empty array [] if absolutely no valid image URLs exist in the entire data structure
+   - sizes: Sizes offered for this product as the store writes them (size picker options, JSON-LD offers), in page order, no duplicates; empty array if none are listed
+   - color: Color of this product/variant as named on the page (e.g. "Navy", "Off-White"); check JSON-LD color, product:color meta and the selected swatch. Empty string if not stated
+   - fit: Cut of the garment if the page states it (title, specs, "Fit:" rows). Return one of: "oversized", "skinny", "boxy", "relaxed", "loose", "slim", "tapered", "straight", "athletic", "regular"; empty string if not stated
    - availability: Stock status. Check og:availability meta tags, JSON-LD availability field, and button/text content ("Add to Cart", "Out of Stock", "Sold Out", "In Stock"). Return one of: "in_stock", "out_of_stock", "limited", "unknown"
 
 FOCUS ON:
-- Use structured data (JSON-LD, Open Graph meta tags) as primary source when available
+- Use structured data (JSON-LD, Open Graph meta tags, schema.org microdata under structured_data.microdata, RDFa product: properties under structured_data.rdfa) as primary source when available
 - If structured data is missing or incomplete, use text content to infer missing information
 - For gender: Look at product title, category, URL path, and any gender-specific keywords
 - For images: Return EVERY image URL from the "images" array - do not filter, do not limit, do not select a subset
 ---
 
-WEBPAGE DATA:
+{}WEBPAGE DATA:
 {}
 "#,
-        serde_json::to_string_pretty(extracted_data).unwrap()
+        hints_block,
+        serde_json::to_string_pretty(extracted_data).unwrap_or_default()
     );
 
     let payload = serde_json::json!({
@@ -506,7 +3180,13 @@ WEBPAGE DATA:
                     },
                     "gender": {
                         "type": "string",
-                        "enum": ["male", "female"]
+                        "enum": ["male", "female", "unisex", "kids"]
+                    },
+                    "fit": {"type": "string"},
+                    "color": {"type": "string"},
+                    "sizes": {
+                        "type": "array",
+                        "items": {"type": "string"}
                     },
                     "image_urls": {
                         "type": "array",
@@ -522,13 +3202,17 @@ WEBPAGE DATA:
         }
     });
 
-    let resp = client.post(&url)
-        .json(&payload)
-        .send()
-        .await
-        .ok()?;
-
-    let result: serde_json::Value = resp.json().await.ok()?;
+    let result = match &ctx.fixtures {
+        Some(fixtures) => mock_json(fixtures, "gemini_extraction", url_for_log, state)?,
+        None => {
+            let resp = ctx.client.post(&url)
+                .json(&payload)
+                .send()
+                .await
+                .ok()?;
+            read_json(resp, state).await?
+        }
+    };
 
     let raw_text = result
         .get("candidates")?
@@ -557,14 +3241,14 @@ WEBPAGE DATA:
     let parsed: serde_json::Value = match serde_json::from_str(&text) {
         Ok(v) => v,
         Err(e) => {
-            println!("[rust_scraper] [gemini] JSON parse error: {e}, attempting to fix...");
-            println!("[rust_scraper] [gemini] Problematic JSON: {}", text);
+            log::warn!("[gemini] JSON parse error: {e}, attempting to fix...");
+            log::debug!("[gemini] Problematic JSON: {}", text);
             let re = Regex::new(r",(\s*[}\]])").unwrap();
             let fixed = re.replace_all(&text, "$1").to_string();
             match serde_json::from_str(&fixed) {
                 Ok(v) => v,
                 Err(_) => {
-                    println!("[rust_scraper] [gemini] Could not fix JSON after attempted repair");
+                    log::warn!("[gemini] Could not fix JSON after attempted repair");
                     return None;
                 }
             }
@@ -579,15 +3263,15 @@ WEBPAGE DATA:
             } else {
                 &snippet
             };
-            println!(
-                "[rust_scraper] [gemini] is_product_page=false url={} response_snippet={}",
+            log::info!(
+                "[gemini] is_product_page=false url={} response_snippet={}",
                 url_for_log, snippet
             );
             return None;
         }
     } else {
-        println!(
-            "[rust_scraper] [gemini] missing is_product_page url={}",
+        log::info!(
+            "[gemini] missing is_product_page url={}",
             url_for_log
         );
         return None;
@@ -602,12 +3286,35 @@ WEBPAGE DATA:
     }
     if let Some(price) = parsed.get("price") {
         // Preserve Gemini's raw price value (string, number, or object).
-        // parse_price() will normalize this into Price { amount, currency }.
+        // parse_price() will normalize this into Price { amount, amount_cents, currency }.
         extracted.insert("price".to_string(), price.clone());
     }
     if let Some(gtype) = parsed.get("garment_type").and_then(|v| v.as_str()) {
         extracted.insert("garment_type".to_string(), serde_json::Value::String(gtype.to_string()));
     }
+    // Passed through even outside the schema enum ("kids", "girls"): a value the
+    // model chose is more useful to callers than a dropped field.
+    if let Some(gender) = parsed
+        .get("gender")
+        .and_then(|v| v.as_str())
+        .map(|g| g.trim().to_lowercase())
+        .filter(|g| !g.is_empty())
+    {
+        extracted.insert("gender".to_string(), serde_json::Value::String(gender));
+    }
+    if let Some(sizes) = parsed.get("sizes").filter(|v| v.as_array().is_some_and(|a| !a.is_empty())) {
+        extracted.insert("sizes".to_string(), sizes.clone());
+    }
+    if let Some(color) = parsed.get("color").and_then(|v| v.as_str()).filter(|c| !c.trim().is_empty()) {
+        extracted.insert("color".to_string(), serde_json::Value::String(color.trim().to_string()));
+    }
+    if let Some(fit) = parsed
+        .get("fit")
+        .and_then(|v| v.as_str())
+        .filter(|f| FIT_VOCABULARY.iter().any(|(fit, _, _)| fit == f))
+    {
+        extracted.insert("fit".to_string(), serde_json::Value::String(fit.to_string()));
+    }
     if let Some(images) = parsed.get("image_urls").and_then(|v| v.as_array()) {
         extracted.insert("image_urls".to_string(), serde_json::Value::Array(images.clone()));
     }
@@ -619,9 +3326,13 @@ WEBPAGE DATA:
 
 async fn call_gemini_for_fast_classification(
     url: &str,
-    client: &wreq::Client,
+    state: &ScrapeState,
+    ctx: &ScraperContext,
 ) -> Option<HashMap<String, serde_json::Value>> {
-    let genai_key = env_var("GENAI_API_KEY")?;
+    let genai_url = ctx.gemini_url(&ctx.config.gemini_model)?;
+    if state.byte_budget_exhausted("gemini fast classification") {
+        return None;
+    }
 
     // Strip query parameters and fragment for cleaner classification
     let cleaned_url = Url::parse(url).ok().map(|parsed| {
@@ -636,11 +3347,6 @@ async fn call_gemini_for_fast_classification(
         .unwrap_or_else(|| url.to_string())
     }).unwrap_or_else(|| url.to_string());
 
-    let genai_url = format!(
-        "https://generativelanguage.googleapis.com/v1beta/models/gemini-flash-lite-latest:generateContent?key={}",
-        genai_key
-    );
-
     let prompt = format!(
         r#"
 Analyze the URL below to determine if it's a SINGLE PRODUCT PAGE or a CATEGORY/LISTING PAGE.
@@ -708,17 +3414,20 @@ URL: {}
         }
     });
 
-    let resp = client.post(&genai_url)
-        .json(&payload)
-        .send()
-        .await
-        .ok()?;
-
-    if !resp.status().is_success() {
-        return None;
-    }
-
-    let result: serde_json::Value = resp.json().await.ok()?;
+    let result = match &ctx.fixtures {
+        Some(fixtures) => mock_json(fixtures, "gemini_fast", url, state)?,
+        None => {
+            let resp = ctx.client.post(&genai_url)
+                .json(&payload)
+                .send()
+                .await
+                .ok()?;
+            if !resp.status().is_success() {
+                return None;
+            }
+            read_json(resp, state).await?
+        }
+    };
     let mut text = result
         .get("candidates")?
         .get(0)?
@@ -758,18 +3467,28 @@ URL: {}
 
 async fn serpapi_search(
     params: &HashMap<String, String>,
-    client: &wreq::Client,
+    state: &ScrapeState,
+    ctx: &ScraperContext,
 ) -> Option<serde_json::Value> {
+    if state.byte_budget_exhausted("serpapi search") {
+        return None;
+    }
+
     let mut url = Url::parse("https://serpapi.com/search").ok()?;
     for (k, v) in params {
         url.query_pairs_mut().append_pair(k, v);
     }
 
-    let resp = client.get(url.as_str()).send().await.ok()?;
+    if let Some(fixtures) = &ctx.fixtures {
+        let engine = params.get("engine").map(String::as_str).unwrap_or("search");
+        return mock_json(fixtures, "serpapi", engine, state);
+    }
+
+    let resp = ctx.client.get(url.as_str()).send().await.ok()?;
     if !resp.status().is_success() {
         return None;
     }
-    resp.json().await.ok()
+    read_json(resp, state).await
 }
 
 // ==================== GEMINI CLASSIFICATION FROM SERPAPI ====================
@@ -778,17 +3497,17 @@ async fn call_gemini_from_serpapi(
     url: &str,
     title: &str,
     snippet: Option<&str>,
-    client: &wreq::Client,
+    state: &ScrapeState,
+    ctx: &ScraperContext,
 ) -> Option<HashMap<String, serde_json::Value>> {
     if title.is_empty() {
         return None;
     }
 
-    let genai_key = env_var("GENAI_API_KEY")?;
-    let genai_url = format!(
-        "https://generativelanguage.googleapis.com/v1beta/models/gemini-2.0-flash:generateContent?key={}",
-        genai_key
-    );
+    let genai_url = ctx.gemini_url(&ctx.config.gemini_classification_model)?;
+    if state.byte_budget_exhausted("gemini classification") {
+        return None;
+    }
 
     let description_line = snippet
         .filter(|s| !s.is_empty())
@@ -801,14 +3520,15 @@ async fn call_gemini_from_serpapi(
 Analyze this product and determine:\n\
 1. The garment type\n\
 2. The product name (without the brand name)\n\
-3. The brand name\n\n\
+3. The brand name\n\
+4. Any sizes the description lists as available (empty array if none)\n\n\
 For garment type, by upper item we mean e.g. shirt, blouse, sweater, jacket, outerwear, blazer, cardigan, vest, tank top etc. \
 By lower item we mean e.g. pants, shorts, jeans, skirt, leggings, trousers etc. \
 By full_body we mean e.g. dress, jumpsuit, long coat, romper, overalls etc. \
 By shoes we mean any footwear like sneakers, boots, sandals, heels, loafers etc. \
 If it's a fashion accessory like a bag, hat, scarf, belt, jewelry, sunglasses etc, then it's other. \
 Only use 'unsupported' if this is clearly not a fashion/clothing/accessory product at all (e.g. electronics, furniture, kitchenware).\n\n\
-Return as JSON with fields 'brand', 'name', and 'garment_type'.",
+Return as JSON with fields 'brand', 'name', 'garment_type' and 'sizes'.",
         title,
         description_line,
         url
@@ -833,6 +3553,10 @@ Return as JSON with fields 'brand', 'name', and 'garment_type'.",
                     "garment_type": {
                         "type": "string",
                         "enum": ["upper", "lower", "full_body", "shoes", "other", "unsupported"]
+                    },
+                    "sizes": {
+                        "type": "array",
+                        "items": {"type": "string"}
                     }
                 },
                 "required": ["brand", "name", "garment_type"]
@@ -840,17 +3564,20 @@ Return as JSON with fields 'brand', 'name', and 'garment_type'.",
         }
     });
 
-    let resp = client.post(&genai_url)
-        .json(&payload)
-        .send()
-        .await
-        .ok()?;
-
-    if !resp.status().is_success() {
-        return None;
-    }
-
-    let result: serde_json::Value = resp.json().await.ok()?;
+    let result = match &ctx.fixtures {
+        Some(fixtures) => mock_json(fixtures, "gemini_classification", url, state)?,
+        None => {
+            let resp = ctx.client.post(&genai_url)
+                .json(&payload)
+                .send()
+                .await
+                .ok()?;
+            if !resp.status().is_success() {
+                return None;
+            }
+            read_json(resp, state).await?
+        }
+    };
     let text = result
         .get("candidates")?
         .get(0)?
@@ -872,6 +3599,9 @@ Return as JSON with fields 'brand', 'name', and 'garment_type'.",
     if let Some(gtype) = parsed.get("garment_type").and_then(|v| v.as_str()) {
         out.insert("garment_type".to_string(), serde_json::Value::String(gtype.to_string()));
     }
+    if let Some(sizes) = parsed.get("sizes").filter(|v| v.as_array().is_some_and(|a| !a.is_empty())) {
+        out.insert("sizes".to_string(), sizes.clone());
+    }
 
     if out.is_empty() {
         None
@@ -882,33 +3612,97 @@ Return as JSON with fields 'brand', 'name', and 'garment_type'.",
 
 // ==================== FETCH FUNCTIONS ====================
 
-async fn fetch_html_curlcffi(original_url: &str, _client: &wreq::Client) -> Option<String> {
-    // Create Chrome-impersonating client with wreq
-    let chrome_client = wreq::Client::builder()
-        .emulation(wreq_util::Emulation::Chrome131)
-        .build()
-        .ok()?;
+/// Sends the request from `build`, retrying up to FETCH_RETRIES times on
+/// timeouts and 429/500/502/503/504 with exponential backoff (250ms, 500ms,
+/// ...). A 429's `Retry-After` seconds replace the backoff, capped at
+/// FETCH_RETRY_AFTER_MAX. Each attempt gets FETCH_ATTEMPT_TIMEOUT_SECS. Once
+/// retries run out the last response is returned so callers still see the
+/// status.
+async fn send_with_retry(
+    label: &str,
+    url: &str,
+    ctx: &ScraperContext,
+    build: impl Fn() -> wreq::RequestBuilder,
+) -> Option<wreq::Response> {
+    let mut attempt = 0;
+    loop {
+        let backoff = FETCH_RETRY_BASE_DELAY * 2u32.saturating_pow(attempt);
+        let delay = match build().timeout(ctx.config.fetch_attempt_timeout).send().await {
+            Ok(resp) => {
+                let code = resp.status().as_u16();
+                if !matches!(code, 429 | 500 | 502 | 503 | 504) || attempt >= ctx.config.fetch_retries {
+                    return Some(resp);
+                }
+                let retry_after = (code == 429)
+                    .then(|| resp.headers().get("retry-after"))
+                    .flatten()
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| v.trim().parse::<u64>().ok())
+                    .map(|secs| Duration::from_secs(secs).min(FETCH_RETRY_AFTER_MAX));
+                log::warn!(
+                    "{} HTTP {}, retry {}/{} url={}",
+                    label,
+                    code,
+                    attempt + 1,
+                    ctx.config.fetch_retries,
+                    url
+                );
+                retry_after.unwrap_or(backoff)
+            }
+            Err(e) if e.is_timeout() && attempt < ctx.config.fetch_retries => {
+                log::warn!(
+                    "{} timed out, retry {}/{} url={}",
+                    label,
+                    attempt + 1,
+                    ctx.config.fetch_retries,
+                    url
+                );
+                backoff
+            }
+            Err(_) => return None,
+        };
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+async fn fetch_html_curlcffi(
+    original_url: &str,
+    state: &ScrapeState,
+    ctx: &ScraperContext,
+) -> Option<String> {
+    let chrome_client = &ctx.chrome_client;
 
     let mut current_url = original_url.to_string();
     let max_redirects = 3;
 
     for _ in 0..=max_redirects {
+        if state.byte_budget_exhausted("curlcffi_gemini fetch") {
+            return None;
+        }
+
         // First attempt with default emulation
-        let mut resp = chrome_client.get(&current_url).send().await.ok()?;
+        let mut resp = send_with_retry("curlcffi_gemini", &current_url, ctx, || {
+            chrome_client
+                .get(&current_url)
+                .header("Accept-Encoding", ACCEPT_ENCODING)
+        })
+        .await?;
         let mut status = resp.status();
 
         // If forbidden, retry with mobile User-Agent
         if status.as_u16() == 403 {
-            println!(
-                "[rust_scraper] curlcffi_gemini HTTP 403, retrying with mobile UA url={}",
+            log::info!(
+                "curlcffi_gemini HTTP 403, retrying with mobile UA url={}",
                 current_url
             );
-            resp = chrome_client
-                .get(&current_url)
-                .header("User-Agent", MOBILE_UA)
-                .send()
-                .await
-                .ok()?;
+            resp = send_with_retry("curlcffi_gemini", &current_url, ctx, || {
+                chrome_client
+                    .get(&current_url)
+                    .header("User-Agent", MOBILE_UA)
+                    .header("Accept-Encoding", ACCEPT_ENCODING)
+            })
+            .await?;
             status = resp.status();
         }
 
@@ -916,13 +3710,22 @@ async fn fetch_html_curlcffi(original_url: &str, _client: &wreq::Client) -> Opti
 
         // Successful response: return body
         if status.is_success() {
-            let text = resp.text().await.ok()?;
-            println!(
-                "[rust_scraper] curlcffi_gemini fetched {} bytes status={} url={}",
+            let text = read_html(resp, state, "curlcffi_gemini").await?;
+            log::info!(
+                event = "fetch",
+                source = "curlcffi_gemini",
+                url = current_url.as_str(),
+                bytes = text.len() as u64,
+                status = status.as_u16();
+                "curlcffi_gemini fetched {} bytes status={} url={}",
                 text.len(),
                 status,
                 current_url
             );
+            if landed_on_login_wall(original_url, &current_url, &text) {
+                state.record_login_redirect("curlcffi_gemini", &current_url).await;
+                return None;
+            }
             return Some(text);
         }
 
@@ -939,16 +3742,16 @@ async fn fetch_html_curlcffi(original_url: &str, _client: &wreq::Client) -> Opti
                     } else {
                         loc_str.to_string()
                     };
-                    println!(
-                        "[rust_scraper] curlcffi_gemini redirect {} -> {}",
+                    log::info!(
+                        "curlcffi_gemini redirect {} -> {}",
                         current_url, next_url
                     );
                     current_url = next_url;
                     continue;
                 }
             }
-            println!(
-                "[rust_scraper] curlcffi_gemini HTTP {} with no usable Location header url={}",
+            log::warn!(
+                "curlcffi_gemini HTTP {} with no usable Location header url={}",
                 code, current_url
             );
             return None;
@@ -956,56 +3759,78 @@ async fn fetch_html_curlcffi(original_url: &str, _client: &wreq::Client) -> Opti
 
         // Non-success, non-redirect: for some hard domains (e.g., therealreal.com),
         // fall back to curl-impersonate.
-        if let Ok(parsed) = Url::parse(&current_url) {
-            if let Some(host) = parsed.host_str() {
-                if host.contains("therealreal.com") {
-                    if let Some(body) = fetch_with_curl_impersonate(&current_url) {
-                        return Some(body);
-                    }
-                }
-            }
+        if let Some(body) = curl_impersonate_fallback(&current_url, state, ctx) {
+            return Some(body);
         }
-        println!(
-            "[rust_scraper] curlcffi_gemini HTTP status={} url={}",
+        log::warn!(
+            "curlcffi_gemini HTTP status={} url={}",
             status, current_url
         );
         return None;
     }
 
-    println!(
-        "[rust_scraper] curlcffi_gemini exceeded redirect limit starting from url={}",
+    log::warn!(
+        "curlcffi_gemini exceeded redirect limit starting from url={}",
         original_url
     );
     None
 }
 
-async fn fetch_html_curlcffi_proxy(original_url: &str) -> Option<String> {
-    let proxy_url = env_var("OXYLABS_PROXY_URL")?;
-    let proxy = wreq::Proxy::all(&proxy_url).ok()?;
-
-    let proxy_client = wreq::Client::builder()
-        .emulation(wreq_util::Emulation::Chrome131)
-        .proxy(proxy)
-        .build()
-        .ok()?;
+async fn fetch_html_curlcffi_proxy(
+    original_url: &str,
+    state: &ScrapeState,
+    ctx: &ScraperContext,
+) -> Option<String> {
+    let pool = ctx.proxy_pool.as_ref()?;
+    let (mut proxy_index, mut proxy_client) = pool.pick();
+    // One rotation per fetch: a blocked or rate-limited proxy gets one retry elsewhere.
+    let mut rotated = false;
 
     let mut current_url = original_url.to_string();
     let max_redirects = 3;
 
     for _ in 0..=max_redirects {
+        if state.byte_budget_exhausted("curlcffi_gemini_proxy fetch") {
+            return None;
+        }
+
         // First attempt with default emulation
-        let mut resp = proxy_client.get(&current_url).send().await.ok()?;
+        let mut resp = proxy_client
+            .get(&current_url)
+            .header("Accept-Encoding", ACCEPT_ENCODING)
+            .send()
+            .await
+            .ok()?;
         let mut status = resp.status();
 
+        if matches!(status.as_u16(), 403 | 429) && !rotated {
+            if let Some((next_index, next_client)) = pool.after(proxy_index) {
+                log::info!(
+                    "curlcffi_gemini_proxy HTTP {} via proxy #{}, rotating to proxy #{} url={}",
+                    status.as_u16(), proxy_index, next_index, current_url
+                );
+                rotated = true;
+                (proxy_index, proxy_client) = (next_index, next_client);
+                resp = proxy_client
+                    .get(&current_url)
+                    .header("Accept-Encoding", ACCEPT_ENCODING)
+                    .send()
+                    .await
+                    .ok()?;
+                status = resp.status();
+            }
+        }
+
         // If forbidden, retry with mobile User-Agent
         if status.as_u16() == 403 {
-            println!(
-                "[rust_scraper] curlcffi_gemini_proxy HTTP 403, retrying with mobile UA url={}",
+            log::info!(
+                "curlcffi_gemini_proxy HTTP 403, retrying with mobile UA url={}",
                 current_url
             );
             resp = proxy_client
                 .get(&current_url)
                 .header("User-Agent", MOBILE_UA)
+                .header("Accept-Encoding", ACCEPT_ENCODING)
                 .send()
                 .await
                 .ok()?;
@@ -1016,13 +3841,24 @@ async fn fetch_html_curlcffi_proxy(original_url: &str) -> Option<String> {
 
         // Successful response: return body
         if status.is_success() {
-            let text = resp.text().await.ok()?;
-            println!(
-                "[rust_scraper] curlcffi_gemini_proxy fetched {} bytes status={} url={}",
+            let text = read_html(resp, state, "curlcffi_gemini_proxy").await?;
+            log::info!(
+                event = "fetch",
+                source = "curlcffi_gemini_proxy",
+                url = current_url.as_str(),
+                bytes = text.len() as u64,
+                status = status.as_u16(),
+                proxy = proxy_index as u64;
+                "curlcffi_gemini_proxy fetched {} bytes status={} proxy=#{} url={}",
                 text.len(),
                 status,
+                proxy_index,
                 current_url
             );
+            if landed_on_login_wall(original_url, &current_url, &text) {
+                state.record_login_redirect("curlcffi_gemini_proxy", &current_url).await;
+                return None;
+            }
             return Some(text);
         }
 
@@ -1038,125 +3874,629 @@ async fn fetch_html_curlcffi_proxy(original_url: &str) -> Option<String> {
                     } else {
                         loc_str.to_string()
                     };
-                    println!(
-                        "[rust_scraper] curlcffi_gemini_proxy redirect {} -> {}",
+                    log::info!(
+                        "curlcffi_gemini_proxy redirect {} -> {}",
                         current_url, next_url
                     );
                     current_url = next_url;
                     continue;
                 }
             }
-            println!(
-                "[rust_scraper] curlcffi_gemini_proxy HTTP {} with no usable Location header url={}",
+            log::warn!(
+                "curlcffi_gemini_proxy HTTP {} with no usable Location header url={}",
                 code, current_url
             );
             return None;
         }
 
         // Non-success, non-redirect: allow curl-impersonate fallback for specific domains
-        if let Ok(parsed) = Url::parse(&current_url) {
-            if let Some(host) = parsed.host_str() {
-                if host.contains("therealreal.com") {
-                    if let Some(body) = fetch_with_curl_impersonate(&current_url) {
-                        return Some(body);
-                    }
-                }
-            }
+        if let Some(body) = curl_impersonate_fallback(&current_url, state, ctx) {
+            return Some(body);
         }
-        println!(
-            "[rust_scraper] curlcffi_gemini_proxy HTTP status={} url={}",
+        log::warn!(
+            "curlcffi_gemini_proxy HTTP status={} url={}",
             status, current_url
         );
         return None;
     }
 
-    println!(
-        "[rust_scraper] curlcffi_gemini_proxy exceeded redirect limit starting from url={}",
+    log::warn!(
+        "curlcffi_gemini_proxy exceeded redirect limit starting from url={}",
         original_url
     );
     None
 }
 
-async fn fetch_cloudflare_worker_data(url: &str, client: &wreq::Client) -> Option<serde_json::Value> {
+async fn fetch_cloudflare_worker_data(
+    url: &str,
+    state: &ScrapeState,
+    ctx: &ScraperContext,
+) -> Option<serde_json::Value> {
     let encoded_url = urlencoding::encode(url);
-    let worker_url = env_var("CLOUDFLARE_WORKER_URL")?;
+    let worker_url = ctx.config.cloudflare_worker_url.as_deref()?;
+    if state.byte_budget_exhausted("cloudflare worker fetch") {
+        return None;
+    }
     let final_url = format!("{}?url={}", worker_url, encoded_url);
 
-    let resp = client.get(&final_url).send().await.ok()?;
+    let body = match &ctx.fixtures {
+        Some(fixtures) => mock_json(fixtures, "worker", url, state)?,
+        None => {
+            let resp = ctx.client.get(&final_url).send().await.ok()?;
+            if !resp.status().is_success() {
+                return None;
+            }
+            read_json(resp, state).await?
+        }
+    };
+    let json = match body {
+        serde_json::Value::Array(items) => {
+            let count = items.len();
+            let collapsed = collapse_worker_products(items)?;
+            log::info!(
+                "cloudflare worker returned {} items for url={}, collapsed to first product",
+                count, url
+            );
+            collapsed
+        }
+        json => json,
+    };
+    if json.get("error").is_some() {
+        return None;
+    }
+
+    Some(json)
+}
+
+/// Bundle pages make the worker answer with an array. Keeps the first
+/// product-shaped object and folds every product's image_urls into it, so
+/// Gemini sees a single product.
+fn collapse_worker_products(items: Vec<serde_json::Value>) -> Option<serde_json::Value> {
+    let mut products = items.into_iter().filter(|item| {
+        ["product_name", "name", "title", "price", "image_urls"]
+            .iter()
+            .any(|key| item.get(*key).is_some_and(|v| !v.is_null()))
+    });
+    let mut first = products.next()?;
+
+    let mut images: Vec<serde_json::Value> = first
+        .get("image_urls")
+        .and_then(|v| v.as_array())
+        .cloned()
+        .unwrap_or_default();
+    for product in products {
+        for image in product.get("image_urls").and_then(|v| v.as_array()).into_iter().flatten() {
+            if !images.contains(image) {
+                images.push(image.clone());
+            }
+        }
+    }
+    if !images.is_empty() {
+        first["image_urls"] = serde_json::Value::Array(images);
+    }
+    Some(first)
+}
+
+/// Total time the optional image HEAD checks may take.
+const IMAGE_VALIDATION_BUDGET: Duration = Duration::from_secs(3);
+const IMAGE_VALIDATION_CONCURRENCY: usize = 8;
+
+/// HEADs each image URL (bounded concurrency) and drops the ones that answer
+/// non-2xx or a non-image content-type. URLs whose check errors out, or that
+/// the server won't HEAD, are kept. Order is preserved; if the budget runs out
+/// the list is returned unchanged.
+async fn validate_image_urls(urls: Vec<String>, budget: Duration, ctx: &ScraperContext) -> Vec<String> {
+    use futures::stream::StreamExt;
+
+    let checks = futures::stream::iter(urls.iter().cloned())
+        .map(|image_url| async move {
+            let resp = match ctx.client.head(&image_url).timeout(budget).send().await {
+                Ok(resp) => resp,
+                Err(_) => return Some(image_url),
+            };
+            let status = resp.status();
+            if matches!(status.as_u16(), 405 | 501) {
+                return Some(image_url);
+            }
+            let content_type = resp
+                .headers()
+                .get("content-type")
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or("")
+                .to_ascii_lowercase();
+            let is_image = content_type.is_empty()
+                || content_type.starts_with("image/")
+                || content_type.starts_with("application/octet-stream");
+            if status.is_success() && is_image {
+                Some(image_url)
+            } else {
+                log::warn!(
+                    "dropping image status={} content-type={} url={}",
+                    status, content_type, image_url
+                );
+                None
+            }
+        })
+        .buffered(IMAGE_VALIDATION_CONCURRENCY)
+        .collect::<Vec<_>>();
+
+    match timeout(budget, checks).await {
+        Ok(results) => results.into_iter().flatten().collect(),
+        Err(_) => {
+            log::warn!(
+                "image validation exceeded {}ms, keeping all {} images",
+                budget.as_millis(),
+                urls.len()
+            );
+            urls
+        }
+    }
+}
+
+/// Total time the optional image dimension checks may take.
+const IMAGE_DIMENSION_BUDGET: Duration = Duration::from_secs(3);
+/// Only the first few images are probed; the rest are kept as-is.
+const IMAGE_DIMENSION_CANDIDATES: usize = 12;
+/// Enough of the file for the JPEG SOF / PNG IHDR / WebP VP8 header, even
+/// behind a large EXIF block.
+const IMAGE_HEADER_BYTES: usize = 64 * 1024;
+
+/// Ranged-GETs the first IMAGE_DIMENSION_CANDIDATES images (bounded
+/// concurrency), reads their dimensions from the header bytes and drops those
+/// whose shorter edge is under `min_edge`. Images whose size can't be read
+/// are kept; if the time or byte budget runs out the list is returned unchanged.
+async fn filter_small_images(
+    urls: Vec<String>,
+    min_edge: u32,
+    budget: Duration,
+    state: &ScrapeState,
+    ctx: &ScraperContext,
+) -> Vec<String> {
+    use futures::stream::StreamExt;
+
+    if state.byte_budget_exhausted("image dimension checks") {
+        return urls;
+    }
+
+    let checks = futures::stream::iter(urls.iter().take(IMAGE_DIMENSION_CANDIDATES).cloned())
+        .map(|image_url| async move {
+            let Some((width, height)) = fetch_image_dimensions(&image_url, budget, state, ctx).await else {
+                return Some(image_url);
+            };
+            if width.min(height) >= min_edge {
+                Some(image_url)
+            } else {
+                log::info!(
+                    "dropping {}x{} image under {}px url={}",
+                    width, height, min_edge, image_url
+                );
+                None
+            }
+        })
+        .buffered(IMAGE_VALIDATION_CONCURRENCY)
+        .collect::<Vec<_>>();
+
+    match timeout(budget, checks).await {
+        Ok(results) => results
+            .into_iter()
+            .flatten()
+            .chain(urls.into_iter().skip(IMAGE_DIMENSION_CANDIDATES))
+            .collect(),
+        Err(_) => {
+            log::warn!(
+                "image dimension checks exceeded {}ms, keeping all {} images",
+                budget.as_millis(),
+                urls.len()
+            );
+            urls
+        }
+    }
+}
+
+/// `(width, height)` from the first IMAGE_HEADER_BYTES of the image. Servers
+/// that ignore the Range header are cut off once that much has arrived. The
+/// bytes read count towards MAX_BYTES_PER_SCRAPE.
+async fn fetch_image_dimensions(
+    image_url: &str,
+    budget: Duration,
+    state: &ScrapeState,
+    ctx: &ScraperContext,
+) -> Option<(u32, u32)> {
+    if state.byte_budget_exhausted("image dimension check") {
+        return None;
+    }
+    let mut resp = ctx
+        .client
+        .get(image_url)
+        .header("Range", format!("bytes=0-{}", IMAGE_HEADER_BYTES - 1))
+        .timeout(budget)
+        .send()
+        .await
+        .ok()?;
     if !resp.status().is_success() {
         return None;
     }
+    let mut head = Vec::with_capacity(IMAGE_HEADER_BYTES);
+    while head.len() < IMAGE_HEADER_BYTES {
+        match resp.chunk().await.ok()? {
+            Some(chunk) => {
+                state.record_bytes(chunk.len());
+                head.extend_from_slice(&chunk);
+            }
+            None => break,
+        }
+    }
+    image::ImageReader::new(std::io::Cursor::new(head))
+        .with_guessed_format()
+        .ok()?
+        .into_dimensions()
+        .ok()
+}
 
-    let json: serde_json::Value = resp.json().await.ok()?;
-    if json.get("error").is_some() {
+// ==================== APPROACH IMPLEMENTATIONS ====================
+
+/// Shared tail of the HTML approaches: run the extractor, merge the deterministic
+/// fields, then (unless running deterministic-only) let Gemini fill the rest.
+async fn extract_and_merge_html(
+    url: &str,
+    html: &str,
+    source: &str,
+    state: &ScrapeState,
+    ctx: &ScraperContext,
+) -> Option<()> {
+    let mut extracted = extract_product_data_from_html(url, html);
+    state.record_page(&extracted, source).await;
+    // Only record_page needs the per-size breakdown; keep it out of the prompt.
+    if let Some(fields) = extracted.as_object_mut() {
+        fields.remove("image_variants");
+    }
+    let structured = structured_fields(&extracted);
+    state.merge_data(&structured, "structured_data").await;
+    let app_state = app_state_fields(&extracted);
+    if !app_state.is_empty() {
+        state.merge_data(&app_state, "app_state").await;
+    }
+    let data_layer = data_layer_fields(&extracted);
+    if !data_layer.is_empty() {
+        state.merge_data(&data_layer, "data_layer").await;
+    }
+
+    let name = structured.get("product_name").and_then(|v| v.as_str());
+    let heuristic = garment_heuristic_fields(name, url, &extracted_breadcrumbs(&extracted));
+    state.merge_data(&heuristic, "garment_heuristic").await;
+
+    if state.options.deterministic_only {
+        return Some(());
+    }
+
+    // Articles/blog posts: skip the LLM call entirely.
+    if let Some(og_type) = non_product_og_type(&extracted) {
+        log::info!(
+            "{} og:type={} with no product data, skipping gemini url={}",
+            source, og_type, url
+        );
+        let mut non_product = state.non_product_og_type.lock().await;
+        if non_product.is_none() {
+            *non_product = Some(og_type.to_string());
+        }
         return None;
     }
 
-    Some(json)
+    let gemini_result = ctx.classifier.extract(url, &extracted, state, ctx).await?;
+    state.merge_data(&gemini_result, source).await;
+    Some(())
+}
+
+/// Sleeps a random FETCH_JITTER_MS delay so origin requests don't fire the
+/// instant a scrape starts. No-op when unset.
+async fn fetch_jitter(approach: &str, ctx: &ScraperContext) {
+    use std::hash::{BuildHasher, Hasher};
+
+    let Some((low, high)) = ctx.config.fetch_jitter_ms else {
+        return;
+    };
+    // RandomState is seeded randomly per instance; plenty for pacing.
+    let noise = std::collections::hash_map::RandomState::new().build_hasher().finish();
+    let delay = low + noise % (high - low).saturating_add(1);
+    if delay == 0 {
+        return;
+    }
+    log::debug!("{} waiting {}ms before fetching", approach, delay);
+    tokio::time::sleep(Duration::from_millis(delay)).await;
+}
+
+lazy_static! {
+    /// Least-recently-used HTML cache keyed by `fetch_cache_key`, holding each
+    /// page with its fetch time. Shared by every scrape in the process, so
+    /// repeated scrapes of a URL reuse its HTML. Capacity comes from each
+    /// scrape's `cache_size`, so a smaller value shrinks it on insert.
+    static ref FETCH_CACHE: std::sync::Mutex<LruCache<String, (String, Instant)>> =
+        std::sync::Mutex::new(LruCache::unbounded());
+}
+
+/// `clean_product_url` without the fragment: tracking params are dropped but
+/// product/variant ids (`?id=`, `?variant=`) and locale path segments are
+/// kept, so different products and regional prices never share an entry.
+fn fetch_cache_key(url: &str) -> String {
+    let cleaned = clean_product_url(url);
+    match Url::parse(&cleaned) {
+        Ok(mut parsed) => {
+            parsed.set_fragment(None);
+            parsed.to_string()
+        }
+        Err(_) => cleaned,
+    }
+}
+
+/// `ctx.fetcher` behind FETCH_CACHE when the scrape's cache_size is non-zero.
+/// Hits skip the fetch jitter and count no bytes. The retry_with_proxy pass
+/// reroutes through the proxy and skips the cache lookup.
+async fn fetch_html_cached(
+    route: FetchRoute,
+    approach: &str,
+    url: &str,
+    state: &ScrapeState,
+    ctx: &ScraperContext,
+) -> Option<String> {
+    let force_proxy = state.force_proxy.load(Ordering::Relaxed);
+    let route = if force_proxy { FetchRoute::Proxy } else { route };
+    let capacity = state.options.cache_size;
+    let key = fetch_cache_key(url);
+    if capacity > 0 && !force_proxy {
+        let cached = FETCH_CACHE.lock().ok().and_then(|mut cache| {
+            let fresh = cache
+                .get(&key)
+                .filter(|(_, fetched_at)| fetched_at.elapsed() <= ctx.config.fetch_cache_ttl)
+                .map(|(html, _)| html.clone());
+            if fresh.is_none() {
+                cache.pop(&key);
+            }
+            fresh
+        });
+        if let Some(html) = cached {
+            log::info!(event = "fetch_cache_hit", source = approach, url = url; "{} served from fetch cache url={}", approach, url);
+            return Some(html);
+        }
+    }
+
+    fetch_jitter(approach, ctx).await;
+    let html = ctx.fetcher.fetch(route, url, state, ctx).await?;
+    if let Some(capacity) = NonZeroUsize::new(capacity) {
+        if let Ok(mut cache) = FETCH_CACHE.lock() {
+            cache.resize(capacity);
+            cache.put(key, (html.clone(), Instant::now()));
+        }
+    }
+    Some(html)
+}
+
+/// Plain (non-emulating) client fetch, no redirect handling beyond the client's.
+async fn fetch_html_plain(url: &str, state: &ScrapeState, ctx: &ScraperContext) -> Option<String> {
+    if state.byte_budget_exhausted("requests_gemini fetch") {
+        return None;
+    }
+    let resp = ctx
+        .client
+        .get(url)
+        .header("Accept-Encoding", ACCEPT_ENCODING)
+        .send()
+        .await
+        .ok()?;
+    if !resp.status().is_success() {
+        return None;
+    }
+    let final_url = resp.uri().to_string();
+    let html = read_html(resp, state, "requests_gemini").await?;
+    if landed_on_login_wall(url, &final_url, &html) {
+        state.record_login_redirect("requests_gemini", &final_url).await;
+        return None;
+    }
+    Some(html)
 }
 
-// ==================== APPROACH IMPLEMENTATIONS ====================
-
 async fn approach_curlcffi_gemini(
     url: &str,
     state: &ScrapeState,
-    client: &wreq::Client,
+    ctx: &ScraperContext,
 ) -> Option<()> {
-    let html = fetch_html_curlcffi(url, client).await?;
-    let extracted = extract_product_data_from_html(url, &html);
-    let gemini_result = call_gemini_for_product_extraction(url, &extracted, client).await?;
-
-    state.merge_data(&gemini_result, "curlcffi_gemini").await;
-    Some(())
+    let html = fetch_html_cached(FetchRoute::Chrome, "curlcffi_gemini", url, state, ctx).await?;
+    extract_and_merge_html(url, &html, "curlcffi_gemini", state, ctx).await
 }
 
 async fn approach_curlcffi_gemini_proxy(
     url: &str,
     state: &ScrapeState,
-    client: &wreq::Client,
+    ctx: &ScraperContext,
 ) -> Option<()> {
-    let html = fetch_html_curlcffi_proxy(url).await?;
-    let extracted = extract_product_data_from_html(url, &html);
-    let gemini_result = call_gemini_for_product_extraction(url, &extracted, client).await?;
-
-    state.merge_data(&gemini_result, "curlcffi_gemini_proxy").await;
-    Some(())
+    fetch_jitter("curlcffi_gemini_proxy", ctx).await;
+    let html = ctx.fetcher.fetch(FetchRoute::Proxy, url, state, ctx).await?;
+    extract_and_merge_html(url, &html, "curlcffi_gemini_proxy", state, ctx).await
 }
 
 async fn approach_requests_gemini(
     url: &str,
     state: &ScrapeState,
-    client: &wreq::Client,
+    ctx: &ScraperContext,
 ) -> Option<()> {
-    let resp = client.get(url).send().await.ok()?;
-    if !resp.status().is_success() {
-        return None;
-    }
-    let html = resp.text().await.ok()?;
+    let html = fetch_html_cached(FetchRoute::Plain, "requests_gemini", url, state, ctx).await?;
+    extract_and_merge_html(url, &html, "requests_gemini", state, ctx).await
+}
+
+async fn approach_cloudflare_gemini(
+    url: &str,
+    state: &ScrapeState,
+    ctx: &ScraperContext,
+) -> Option<()> {
+    let data = fetch_cloudflare_worker_data(url, state, ctx).await?;
+
+    // Merge what the worker already parsed so this approach contributes even if
+    // the Gemini call fails.
+    let fields = worker_fields(&data);
+    state.merge_data(&fields, "cloudflare_structured").await;
+    let name = fields.get("product_name").and_then(|v| v.as_str());
+    let heuristic = garment_heuristic_fields(name, url, &extracted_breadcrumbs(&data));
+    state.merge_data(&heuristic, "garment_heuristic").await;
 
-    let extracted = extract_product_data_from_html(url, &html);
-    let gemini_result = call_gemini_for_product_extraction(url, &extracted, client).await?;
+    let gemini_result = ctx.classifier.extract(url, &data, state, ctx).await?;
 
-    state.merge_data(&gemini_result, "requests_gemini").await;
+    state.merge_data(&gemini_result, "cloudflare_gemini").await;
     Some(())
 }
 
-async fn approach_cloudflare_gemini(
+/// Shopify storefronts serve every product as clean JSON at
+/// `<product-url>.json`, which beats LLM extraction across the long tail of
+/// Shopify shops. Only `/products/<handle>` URLs are tried; other platforms
+/// answer that path with HTML or a 404, which fails the approach quietly.
+async fn approach_shopify_json(
     url: &str,
     state: &ScrapeState,
-    client: &wreq::Client,
+    ctx: &ScraperContext,
 ) -> Option<()> {
-    let data = fetch_cloudflare_worker_data(url, client).await?;
-    let gemini_result = call_gemini_for_product_extraction(url, &data, client).await?;
+    let json_url = shopify_product_json_url(url)?;
+    if state.byte_budget_exhausted("shopify json fetch") {
+        return None;
+    }
 
-    state.merge_data(&gemini_result, "cloudflare_gemini").await;
+    let body = match &ctx.fixtures {
+        Some(fixtures) => mock_json(fixtures, "shopify", url, state)?,
+        None => {
+            fetch_jitter("shopify_json", ctx).await;
+            let resp = ctx
+                .client
+                .get(&json_url)
+                .header("Accept", "application/json")
+                .send()
+                .await
+                .ok()?;
+            if !resp.status().is_success() {
+                return None;
+            }
+            read_json(resp, state).await?
+        }
+    };
+
+    let fields = shopify_fields(url, body.get("product")?);
+    if fields.is_empty() {
+        return None;
+    }
+    state.merge_data(&fields, "shopify_json").await;
     Some(())
 }
 
+/// `https://shop.com/en-gb/products/tee?variant=1` → `https://shop.com/en-gb/products/tee.json`;
+/// None unless the path has a `products/<handle>` pair.
+fn shopify_product_json_url(url: &str) -> Option<String> {
+    let mut parsed = Url::parse(url).ok()?;
+    let segments: Vec<&str> = parsed.path().split('/').filter(|s| !s.is_empty()).collect();
+    let pos = segments.iter().position(|s| s.eq_ignore_ascii_case("products"))?;
+    let handle = segments.get(pos + 1)?;
+    let handle = handle
+        .strip_suffix(".json")
+        .or_else(|| handle.strip_suffix(".js"))
+        .unwrap_or(handle);
+    let path = format!("/{}/{}.json", segments[..=pos].join("/"), handle);
+    parsed.set_path(&path);
+    parsed.set_query(None);
+    parsed.set_fragment(None);
+    Some(parsed.to_string())
+}
+
+/// Maps a Shopify `product` object: title → product_name, vendor → brand,
+/// the `?variant=` variant's price (else the first variant's) → price, and
+/// images → image_urls. The variant price is bare, so its currency comes from
+/// `presentment_prices` when the shop lists them and is otherwise left unset.
+fn shopify_fields(url: &str, product: &serde_json::Value) -> HashMap<String, serde_json::Value> {
+    let mut fields = HashMap::new();
+    let text = |key: &str| {
+        product
+            .get(key)
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+    };
+
+    if let Some(title) = text("title") {
+        fields.insert("product_name".to_string(), serde_json::Value::String(title.to_string()));
+    }
+    if let Some(vendor) = text("vendor") {
+        fields.insert("brand".to_string(), serde_json::Value::String(vendor.to_string()));
+    }
+
+    let variants = product.get("variants").and_then(|v| v.as_array());
+    let selected_id = Url::parse(url).ok().and_then(|parsed| {
+        parsed
+            .query_pairs()
+            .find(|(key, _)| key == "variant")
+            .map(|(_, value)| value.into_owned())
+    });
+    let variant = variants.and_then(|variants| {
+        selected_id
+            .and_then(|id| {
+                variants
+                    .iter()
+                    .find(|variant| variant.get("id").is_some_and(|v| v.to_string().trim_matches('"') == id))
+            })
+            .or_else(|| variants.first())
+    });
+    if let Some(variant) = variant.filter(|variant| variant.get("price").is_some()) {
+        let mut price = parse_price(&variant["price"]);
+        price.currency = variant
+            .pointer("/presentment_prices/0/price/currency_code")
+            .and_then(|v| v.as_str())
+            .and_then(normalize_currency_code);
+        if price.amount.is_some() {
+            fields.insert(
+                "price".to_string(),
+                serde_json::json!({ "amount": price.amount, "amount_cents": price.amount_cents, "currency": price.currency }),
+            );
+        }
+    }
+
+    let images = usable_image_urls(product.get("images"));
+    if !images.is_empty() {
+        fields.insert("image_urls".to_string(), serde_json::Value::Array(images));
+    }
+
+    // options: [{"name": "Color", "position": 1, "values": [...]}]; the
+    // variant's `option<position>` is its value for that option.
+    let color_option = product.get("options").and_then(|v| v.as_array()).and_then(|options| {
+        options.iter().find(|option| {
+            option
+                .get("name")
+                .and_then(|v| v.as_str())
+                .is_some_and(|name| matches!(name.trim().to_lowercase().as_str(), "color" | "colour"))
+        })
+    });
+    if let Some(option) = color_option {
+        let colors: Vec<serde_json::Value> = option
+            .get("values")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+            .filter(|v| v.as_str().is_some_and(|s| !s.trim().is_empty()))
+            .cloned()
+            .collect();
+        if !colors.is_empty() {
+            fields.insert("colors".to_string(), serde_json::Value::Array(colors));
+        }
+        let selected = option
+            .get("position")
+            .and_then(|v| v.as_u64())
+            .and_then(|position| variant?.get(format!("option{}", position)))
+            .and_then(|v| v.as_str())
+            .filter(|s| !s.trim().is_empty());
+        if let Some(selected) = selected {
+            fields.insert("selected_color".to_string(), serde_json::Value::String(selected.to_string()));
+        }
+    }
+
+    fields
+}
+
 async fn approach_serpapi_google(
     url: &str,
     state: &ScrapeState,
-    client: &wreq::Client,
+    ctx: &ScraperContext,
 ) -> Option<()> {
     let cleaned = clean_product_url(url);
 
@@ -1165,12 +4505,12 @@ async fn approach_serpapi_google(
     params.insert("q".to_string(), cleaned.clone());
     params.insert("gl".to_string(), "us".to_string());
     params.insert("hl".to_string(), "en".to_string());
-    let serp_key = env_var("SERPAPI_KEY")?;
+    let serp_key = ctx.config.serpapi_key.clone()?;
     params.insert("api_key".to_string(), serp_key);
     params.insert("google_domain".to_string(), "google.com".to_string());
 
     // First attempt
-    let mut result = serpapi_search(&params, client).await;
+    let mut result = serpapi_search(&params, state, ctx).await;
 
     // If no shopping_results, retry with normalized path like Python
     if result
@@ -1181,7 +4521,7 @@ async fn approach_serpapi_google(
         if let Some(normalized) = normalize_url_path(&cleaned) {
             if normalized != cleaned {
                 params.insert("q".to_string(), normalized);
-                result = serpapi_search(&params, client).await;
+                result = serpapi_search(&params, state, ctx).await;
             }
         }
     }
@@ -1203,7 +4543,7 @@ async fn approach_serpapi_google(
     // Optionally call Gemini classification on the SerpAPI title/snippet
     if let Some(title) = first.get("title").and_then(|v| v.as_str()) {
         let snippet = first.get("snippet").and_then(|v| v.as_str());
-        if let Some(classified) = call_gemini_from_serpapi(url, title, snippet, client).await {
+        if let Some(classified) = ctx.classifier.classify_title(url, title, snippet, state, ctx).await {
             state.merge_data(&classified, "gemini_classification").await;
         }
     }
@@ -1214,17 +4554,21 @@ async fn approach_serpapi_google(
 async fn approach_serpapi_images_url(
     url: &str,
     state: &ScrapeState,
-    client: &wreq::Client,
+    ctx: &ScraperContext,
 ) -> Option<()> {
+    if state.has_enough_images("serpapi_images_url").await {
+        return None;
+    }
+
     let mut params = HashMap::new();
     params.insert("engine".to_string(), "google_images_light".to_string());
     params.insert("q".to_string(), url.to_string());
     params.insert("gl".to_string(), "us".to_string());
     params.insert("hl".to_string(), "en".to_string());
-    let serp_key = env_var("SERPAPI_KEY")?;
+    let serp_key = ctx.config.serpapi_key.clone()?;
     params.insert("api_key".to_string(), serp_key);
 
-    let result = serpapi_search(&params, client).await?;
+    let result = serpapi_search(&params, state, ctx).await?;
     let images = result.get("images_results")?.as_array()?;
 
     for img in images {
@@ -1244,11 +4588,16 @@ async fn approach_serpapi_images_url(
 async fn approach_serpapi_images_title(
     url: &str,
     state: &ScrapeState,
-    client: &wreq::Client,
+    ctx: &ScraperContext,
 ) -> Option<()> {
-    // Wait up to 8 seconds for product name to be available
+    // Wait up to 8 seconds for product name to be available, giving up early
+    // once a strong source has filled the images.
     let mut attempts = 0;
     let product_name = loop {
+        if state.has_strong_images().await {
+            log::info!("serpapi_images_title skipped, images already from a strong source");
+            return None;
+        }
         let product = state.product.lock().await;
         if let Some(name) = &product.product_name {
             break name.clone();
@@ -1262,6 +4611,10 @@ async fn approach_serpapi_images_title(
         tokio::time::sleep(Duration::from_millis(100)).await;
     };
 
+    if state.has_enough_images("serpapi_images_title").await {
+        return None;
+    }
+
     let domain = Url::parse(url).ok()?.host_str()?.to_string();
     let query = format!("\"{}\" site:{}", product_name, domain);
 
@@ -1270,11 +4623,15 @@ async fn approach_serpapi_images_title(
     params.insert("q".to_string(), query);
     params.insert("gl".to_string(), "us".to_string());
     params.insert("hl".to_string(), "en".to_string());
-    let serp_key = env_var("SERPAPI_KEY")?;
+    let serp_key = ctx.config.serpapi_key.clone()?;
     params.insert("api_key".to_string(), serp_key);
 
-    let result = serpapi_search(&params, client).await?;
+    let result = serpapi_search(&params, state, ctx).await?;
     let images = result.get("images_results")?.as_array()?;
+    // A strong source may have landed while the search was in flight.
+    if state.has_strong_images().await {
+        return None;
+    }
 
     for img in images {
         if let Some(original) = img.get("original").and_then(|v| v.as_str()) {
@@ -1291,216 +4648,948 @@ async fn approach_serpapi_images_title(
 async fn approach_gemini_fast(
     url: &str,
     state: &ScrapeState,
-    client: &wreq::Client,
+    ctx: &ScraperContext,
 ) -> Option<()> {
-    let result = call_gemini_for_fast_classification(url, client).await?;
+    let result = ctx.classifier.classify_url(url, state, ctx).await?;
     state.merge_data(&result, "gemini_fast").await;
     Some(())
 }
 
 // ==================== MAIN ORCHESTRATOR ====================
 
-async fn scrape_product_rust(url: String, overall_timeout_sec: f64) -> Result<ProductData, String> {
-    let state = ScrapeState::new();
-    println!(
-        "[rust_scraper] start scrape url={} timeout_sec={}",
+/// Every approach the orchestrator knows, in spawn order.
+const APPROACHES: &[&str] = &[
+    "shopify_json",
+    "gemini_fast",
+    "curlcffi_gemini",
+    "curlcffi_gemini_proxy",
+    "requests_gemini",
+    "cloudflare_gemini",
+    "serpapi_google",
+    "serpapi_images_url",
+    "serpapi_images_title",
+];
+
+/// Approaches that fetch from the store itself; respect_robots gates these.
+/// SerpAPI, the Cloudflare worker and gemini_fast never touch the origin.
+const ORIGIN_FETCH_APPROACHES: &[&str] = &["shopify_json", "curlcffi_gemini", "curlcffi_gemini_proxy", "requests_gemini"];
+
+const ROBOTS_FETCH_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// The host's robots.txt rules for ROBOTS_PRODUCT_TOKEN. A missing file (4xx) allows
+/// everything; a 5xx or failed fetch disallows everything (RFC 9309).
+async fn fetch_robots_rules(url: &str, ctx: &ScraperContext) -> RobotsRules {
+    let Some(robots_url) = Url::parse(url).ok().and_then(|parsed| parsed.join("/robots.txt").ok()) else {
+        return RobotsRules::default();
+    };
+    if let Some(fixtures) = &ctx.fixtures {
+        return fixtures
+            .robots(robots_url.as_str())
+            .map(|body| RobotsRules::parse(&body, ROBOTS_PRODUCT_TOKEN))
+            .unwrap_or_default();
+    }
+
+    let fetch = async {
+        let resp = ctx
+            .client
+            .get(robots_url.as_str())
+            .header("User-Agent", MOBILE_UA)
+            .send()
+            .await
+            .ok()?;
+        let status = resp.status();
+        if status.is_client_error() {
+            return Some(RobotsRules::default());
+        }
+        if !status.is_success() {
+            return None;
+        }
+        let body = resp.text().await.ok()?;
+        Some(RobotsRules::parse(&body, ROBOTS_PRODUCT_TOKEN))
+    };
+    match timeout(ROBOTS_FETCH_TIMEOUT, fetch).await {
+        Ok(Some(rules)) => rules,
+        _ => {
+            log::warn!("robots.txt unreachable, treating {} as disallowed", robots_url);
+            RobotsRules::disallow_all()
+        }
+    }
+}
+
+async fn run_approach(name: &str, url: &str, state: &ScrapeState, ctx: &ScraperContext) -> Option<()> {
+    let span_start = Instant::now();
+    log::info!(event = "approach_start", source = name, url = url; "approach {} started for url={}", name, url);
+    let work = async {
+        if ORIGIN_FETCH_APPROACHES.contains(&name) && state.robots_disallows(url).await {
+            log::info!("{} skipped, robots.txt disallows url={}", name, url);
+            return None;
+        }
+        match name {
+            "shopify_json" => approach_shopify_json(url, state, ctx).await,
+            "gemini_fast" => approach_gemini_fast(url, state, ctx).await,
+            "curlcffi_gemini" => approach_curlcffi_gemini(url, state, ctx).await,
+            "curlcffi_gemini_proxy" => approach_curlcffi_gemini_proxy(url, state, ctx).await,
+            "requests_gemini" => approach_requests_gemini(url, state, ctx).await,
+            "cloudflare_gemini" => approach_cloudflare_gemini(url, state, ctx).await,
+            "serpapi_google" => approach_serpapi_google(url, state, ctx).await,
+            "serpapi_images_url" => approach_serpapi_images_url(url, state, ctx).await,
+            "serpapi_images_title" => approach_serpapi_images_title(url, state, ctx).await,
+            _ => None,
+        }
+    };
+    let mut timed_out = false;
+    let result = match ctx.config.approach_timeouts.get(name) {
+        Some(limit) => timeout(*limit, work).await.unwrap_or_else(|_| {
+            log::warn!(
+                "approach {} timed out after {}ms",
+                name,
+                limit.as_millis()
+            );
+            timed_out = true;
+            None
+        }),
+        None => work.await,
+    };
+    let elapsed_ms = span_start.elapsed().as_millis();
+    state.approach_runs.lock().await.push(ApproachRun {
+        name: name.to_string(),
+        success: result.is_some(),
+        status: match (result.is_some(), timed_out) {
+            (true, _) => "success",
+            (false, true) => "timeout",
+            (false, false) => "failed",
+        },
+        elapsed_ms: elapsed_ms as u64,
+    });
+    log::info!(
+        event = "approach_finish",
+        source = name,
+        url = url,
+        elapsed_ms = elapsed_ms as u64,
+        success = result.is_some();
+        "approach {} finished in {}ms success={}",
+        name,
+        elapsed_ms,
+        result.is_some()
+    );
+    emit_approach_metric(name, result.is_some(), elapsed_ms);
+    result
+}
+
+async fn scrape_product_rust(
+    url: String,
+    options: ScrapeOptions,
+    ctx: ScraperContext,
+) -> Result<ScrapeOutcome, ScrapeFailure> {
+    let overall_timeout_sec = options.timeout_sec;
+    let include_text_content = options.include_text_content;
+    let include_attribution = options.include_attribution;
+    let deterministic_only = options.deterministic_only;
+    let use_serpapi = options.use_serpapi;
+    let state = ScrapeState::new(options, ctx.config.clone());
+    log::info!(
+        event = "scrape_start", url = url.as_str();
+        "start scrape url={} timeout_sec={}",
         url, overall_timeout_sec
     );
-    let client = wreq::Client::builder()
-        .timeout(Duration::from_secs(15))
-        .build()
-        .map_err(|e| e.to_string())?;
-
-    let approaches = vec![
-        ("gemini_fast", url.clone()),
-        ("curlcffi_gemini", url.clone()),
-        ("curlcffi_gemini_proxy", url.clone()),
-        ("requests_gemini", url.clone()),
-        ("cloudflare_gemini", url.clone()),
-        ("serpapi_google", url.clone()),
-        ("serpapi_images_url", url.clone()),
-        ("serpapi_images_title", url.clone()),
-    ];
+
+    let mut approaches: Vec<&'static str> = APPROACHES
+        .iter()
+        .copied()
+        .filter(|name| {
+            !deterministic_only
+                || matches!(
+                    *name,
+                    "shopify_json" | "curlcffi_gemini" | "curlcffi_gemini_proxy" | "requests_gemini"
+                )
+        })
+        .filter(|name| use_serpapi || !name.starts_with("serpapi_"))
+        .filter(|name| {
+            state
+                .options
+                .enabled_approaches
+                .as_ref()
+                .map_or(true, |enabled| enabled.iter().any(|e| e == name))
+        })
+        .collect();
+    if state.options.respect_robots {
+        let rules = fetch_robots_rules(&url, &ctx).await;
+        *state.robots.lock().await = Some(rules);
+        if state.robots_disallows(&url).await {
+            log::info!(
+                "robots.txt disallows url={}, only third-party approaches will run",
+                url
+            );
+        }
+    }
+    // One HTML approach is enough for the proxy pass: both would fetch the same
+    // URL through the same proxy. The Chrome-emulating one is preferred.
+    let proxy_retry_approach: Option<&'static str> = ["curlcffi_gemini", "requests_gemini"]
+        .into_iter()
+        .find(|name| approaches.contains(name));
+
+    // Fast path for trusted domains: run the primary approach alone and skip the
+    // race entirely if it already produced a complete, strong result.
+    if let Some(primary) = state.options.primary_approach.as_deref() {
+        match approaches.iter().position(|name| *name == primary) {
+            Some(pos) => {
+                let name = approaches.remove(pos);
+                let primary_timeout = Duration::from_secs_f64(overall_timeout_sec);
+                let _ = timeout(primary_timeout, run_approach(name, &url, &state, &ctx)).await;
+                if state.is_complete().await && state.has_strong_source().await {
+                    log::info!(
+                        "primary approach {} complete, skipping the rest",
+                        name
+                    );
+                    approaches.clear();
+                }
+            }
+            None => log::info!(
+                "primary approach {} not enabled for this scrape, ignoring",
+                primary
+            ),
+        }
+    }
 
     // Spawn all approaches concurrently
+    let spawned = approaches.clone();
     let mut handles = Vec::new();
-    for (name, url_clone) in approaches {
+    for name in approaches {
+        let url_clone = url.clone();
         let state_clone = state.clone();
-        let client_clone = client.clone();
+        let ctx_clone = ctx.clone();
 
         let handle = tokio::spawn(async move {
-            let span_start = Instant::now();
-            println!(
-                "[rust_scraper] approach {} started for url={}",
-                name, url_clone
-            );
-            let result = match name {
-                "gemini_fast" => approach_gemini_fast(&url_clone, &state_clone, &client_clone).await,
-                "curlcffi_gemini" => approach_curlcffi_gemini(&url_clone, &state_clone, &client_clone).await,
-                "curlcffi_gemini_proxy" => approach_curlcffi_gemini_proxy(&url_clone, &state_clone, &client_clone).await,
-                "requests_gemini" => approach_requests_gemini(&url_clone, &state_clone, &client_clone).await,
-                "cloudflare_gemini" => approach_cloudflare_gemini(&url_clone, &state_clone, &client_clone).await,
-                "serpapi_google" => approach_serpapi_google(&url_clone, &state_clone, &client_clone).await,
-                "serpapi_images_url" => approach_serpapi_images_url(&url_clone, &state_clone, &client_clone).await,
-                "serpapi_images_title" => approach_serpapi_images_title(&url_clone, &state_clone, &client_clone).await,
-                _ => None,
-            };
-            let span_elapsed = span_start.elapsed().as_millis();
-            println!(
-                "[rust_scraper] approach {} finished in {}ms success={}",
-                name,
-                span_elapsed,
-                result.is_some()
-            );
+            let result = run_approach(name, &url_clone, &state_clone, &ctx_clone).await;
             (name, result)
         });
         handles.push(handle);
     }
 
-    // Race logic: check completion every 100ms
-    let timeout_duration = Duration::from_secs_f64(overall_timeout_sec);
-    let race_result: Result<Result<(), ()>, _> = timeout(timeout_duration, async {
+    // Race logic: check completion every 100ms, within what's left of the budget
+    let timeout_duration = Duration::from_secs_f64(overall_timeout_sec)
+        .saturating_sub(Duration::from_millis(state.elapsed_ms() as u64));
+    let race_result = timeout(timeout_duration, async {
         loop {
-            if state.is_complete().await {
+            let complete = state.is_complete().await;
+            let strong = state.has_strong_source().await;
+            if complete {
                 let elapsed = state.elapsed_ms();
                 // Prefer to wait for a strong HTML+Gemini source if possible.
-                if state.has_strong_source().await {
-                    return Ok::<(), ()>(());
+                if strong {
+                    return CompletionReason::StrongComplete;
                 }
                 // But don't wait forever: if we've already waited > 5s with
                 // no strong source, accept the best complete data available.
                 if elapsed > 5000 {
-                    return Ok::<(), ()>(());
+                    return CompletionReason::WeakCompleteGrace;
                 }
             }
+            // Nothing left that could add data.
+            if handles.iter().all(|h| h.is_finished()) {
+                return match (complete, strong) {
+                    (true, true) => CompletionReason::StrongComplete,
+                    (true, false) => CompletionReason::WeakCompleteGrace,
+                    (false, _) => CompletionReason::Error,
+                };
+            }
             tokio::time::sleep(Duration::from_millis(100)).await;
         }
     }).await;
 
     let total_elapsed = state.elapsed_ms();
-    match &race_result {
-        Ok(_) => println!(
-            "[rust_scraper] scrape completed in {}ms before timeout",
-            total_elapsed
-        ),
-        Err(_) => println!(
-            "[rust_scraper] scrape hit overall timeout at {}ms",
-            total_elapsed
-        ),
-    }
+    let global_timeout_fired = race_result.is_err();
+    let mut completion_reason = race_result.unwrap_or(CompletionReason::Timeout);
+    *state.completion_reason.lock().await = Some(completion_reason);
+    log::info!(
+        event = "scrape_stop",
+        url = url.as_str(),
+        elapsed_ms = total_elapsed as u64,
+        bytes = state.bytes_downloaded(),
+        reason = completion_reason.as_str();
+        "scrape stopped at {}ms reason={}",
+        total_elapsed,
+        completion_reason.as_str()
+    );
 
     // Abort unfinished tasks
     for handle in &handles {
         handle.abort();
     }
+    {
+        let mut runs = state.approach_runs.lock().await;
+        for name in spawned {
+            if !runs.iter().any(|run| run.name == name) {
+                runs.push(ApproachRun {
+                    name: name.to_string(),
+                    success: false,
+                    status: "cancelled",
+                    elapsed_ms: total_elapsed as u64,
+                });
+            }
+        }
+    }
+
+    // Opt-in second pass: direct fetches were likely soft-blocked, so re-drive
+    // one HTML approach through the proxy with what's left of the budget.
+    let proxy_retry = proxy_retry_approach.filter(|_| state.options.retry_with_proxy && ctx.proxy_pool.is_some());
+    if let Some(retry_approach) = proxy_retry {
+        if !state.is_complete().await && !state.has_strong_source().await {
+            let remaining = Duration::from_secs_f64(overall_timeout_sec)
+                .saturating_sub(Duration::from_millis(state.elapsed_ms() as u64));
+            if !remaining.is_zero() {
+                log::info!(
+                    "retrying {} through proxy with {}ms left url={}",
+                    retry_approach,
+                    remaining.as_millis(),
+                    url
+                );
+                state.force_proxy.store(true, Ordering::Relaxed);
+                let _ = timeout(remaining, run_approach(retry_approach, &url, &state, &ctx)).await;
+                if state.is_complete().await {
+                    completion_reason = if state.has_strong_source().await {
+                        CompletionReason::StrongComplete
+                    } else {
+                        CompletionReason::WeakCompleteGrace
+                    };
+                    *state.completion_reason.lock().await = Some(completion_reason);
+                }
+            }
+        }
+    }
+
+    // Backstop: if nothing classified the garment (e.g. every Gemini call failed),
+    // fall back to keywords from whatever name, URL and breadcrumbs we have.
+    let needs_garment_type = state.product.lock().await.garment_type.is_none();
+    if needs_garment_type {
+        let name = state.product.lock().await.product_name.clone();
+        let breadcrumbs = state
+            .page
+            .lock()
+            .await
+            .text_content
+            .as_ref()
+            .map(extracted_breadcrumbs)
+            .unwrap_or_default();
+        let heuristic = garment_heuristic_fields(name.as_deref(), &url, &breadcrumbs);
+        state.merge_data(&heuristic, "garment_heuristic").await;
+    }
 
     // Get final product data
-    let product = state.product.lock().await.clone();
+    let mut product = state.product.lock().await.clone();
     let missing = product.missing_fields();
-    println!(
-        "[rust_scraper] final product missing_fields={:?}",
+    log::info!(
+        "final product missing_fields={:?}",
         missing
     );
 
+    // The origin was off-limits and the third-party sources came up empty.
+    let found_nothing =
+        product.product_name.is_none() && !product.has_price() && product.image_urls.is_empty();
+    if found_nothing && state.robots_disallows(&url).await {
+        return Err(ScrapeFailure::new(ScrapeError::RobotsDisallowed { url }, &product));
+    }
+
+    // Gated product: the page fetches landed on a login wall and nothing else
+    // filled the gap, so a partial result would be misleading.
+    if let Some(login_url) = state.login_redirect.lock().await.clone() {
+        if !product.is_complete() {
+            return Err(ScrapeFailure::new(
+                ScrapeError::LoginRequired { url, login_url },
+                &product,
+            ));
+        }
+    }
+
+    if let Some(og_type) = state.non_product_og_type.lock().await.clone() {
+        if !product.is_complete() {
+            return Err(ScrapeFailure::new(
+                ScrapeError::NotFashionProduct { url, og_type: Some(og_type) },
+                &product,
+            ));
+        }
+    }
+
     // Validate garment_type similar to Python scraper_service_v3:
     // - "unsupported" => NotFashionProductError
     // - "other" or invalid => UnsupportedProductError
+    // unless raise_on_unsupported is off, in which case the caller gets the data
+    // back with the type (and missing_flags.unsupported) to decide on.
     if let Some(ref gtype) = product.garment_type {
         match gtype.as_str() {
-            "unsupported" => {
-                return Err(format!(
-                    "NotFashionProductError: The page at {} is not a fashion product page",
-                    url
-                ));
-            }
-            "other" => {
-                return Err(format!(
-                    "UnsupportedProductError: The product at {} is not a supported fashion item (garment_type: other)",
-                    url
-                ));
-            }
             "upper" | "lower" | "full_body" | "shoes" => {
                 // ok
             }
+            _ if !state.options.raise_on_unsupported => {
+                log::info!(
+                    "returning garment_type={} without raising url={}",
+                    gtype, url
+                );
+            }
+            "unsupported" => {
+                return Err(ScrapeFailure::new(
+                    ScrapeError::NotFashionProduct { url, og_type: None },
+                    &product,
+                ));
+            }
+            // "other" or anything unrecognized
             _ => {
-                return Err(format!(
-                    "UnsupportedProductError: Could not determine garment type for product at {} (got: {})",
-                    url, gtype
+                return Err(ScrapeFailure::new(
+                    ScrapeError::UnsupportedProduct { url, garment_type: Some(gtype.clone()) },
+                    &product,
                 ));
             }
         }
-    } else {
-        return Err(format!(
-            "UnsupportedProductError: Could not determine garment type for product at {} (got: None)",
-            url
-        ));
+    } else if !deterministic_only {
+        // Deterministic-only runs return whatever was found, even without a type.
+        // With nothing found at all, say why rather than blaming the garment type.
+        let error = if !found_nothing {
+            ScrapeError::UnsupportedProduct { url, garment_type: None }
+        } else if state.bytes_downloaded() == 0 {
+            ScrapeError::NetworkError(format!("No source returned a response for {}", url))
+        } else if completion_reason == CompletionReason::Timeout {
+            ScrapeError::Timeout { url }
+        } else {
+            ScrapeError::AllApproachesFailed { url }
+        };
+        return Err(ScrapeFailure::new(error, &product));
     }
 
-    Ok(product)
-}
+    // A price in another currency usually means the store geo-detected a
+    // different market.
+    let price_currency = product.price.as_ref().and_then(|p| p.currency.clone());
+    let currency_mismatch = match (&state.options.expected_currency, &price_currency) {
+        (Some(expected), Some(actual)) => expected != actual,
+        _ => false,
+    };
+    if currency_mismatch {
+        log::warn!(
+            "currency mismatch url={} expected={:?} got={:?}",
+            url, state.options.expected_currency, price_currency
+        );
+        if state.options.strict_currency {
+            return Err(ScrapeFailure::new(
+                ScrapeError::CurrencyMismatch {
+                    url,
+                    expected: state.options.expected_currency.clone().unwrap_or_default(),
+                    actual: price_currency.unwrap_or_default(),
+                },
+                &product,
+            ));
+        }
+    }
+
+    let likely_mismatch = likely_url_mismatch(&url, &product);
+    if likely_mismatch {
+        log::warn!(
+            "result may describe another product url={} name={:?} brand={:?}",
+            url, product.product_name, product.brand
+        );
+    }
+
+    if ctx.config.validate_image_urls && ctx.fixtures.is_none() && !product.image_urls.is_empty() {
+        // Never let validation push the scrape past its overall budget.
+        let remaining = Duration::from_secs_f64(overall_timeout_sec)
+            .saturating_sub(Duration::from_millis(state.elapsed_ms() as u64));
+        let budget = remaining.min(IMAGE_VALIDATION_BUDGET);
+        if !budget.is_zero() {
+            product.image_urls = validate_image_urls(product.image_urls, budget, &ctx).await;
+        }
+    }
 
-// ==================== PYO3 BINDINGS ====================
+    if let Some(min_edge) = ctx.config.min_image_edge {
+        if ctx.fixtures.is_none() && !product.image_urls.is_empty() {
+            let remaining = Duration::from_secs_f64(overall_timeout_sec)
+                .saturating_sub(Duration::from_millis(state.elapsed_ms() as u64));
+            let budget = remaining.min(IMAGE_DIMENSION_BUDGET);
+            if !budget.is_zero() {
+                product.image_urls = filter_small_images(product.image_urls, min_edge, budget, &state, &ctx).await;
+            }
+        }
+    }
+
+    // Before the diff: a baseline from an earlier result already has it filled.
+    product.fill_selected_color();
 
-#[pyfunction]
-#[pyo3(signature = (url, timeout_secs=None))]
-fn scrape_url(py: Python, url: String, timeout_secs: Option<f64>) -> PyResult<PyObject> {
-    let timeout_sec = timeout_secs.unwrap_or(30.0);
+    let changes = state
+        .options
+        .baseline
+        .as_ref()
+        .map(|baseline| product.changes_since(baseline));
+
+    let sizes_normalized = state
+        .options
+        .normalize_sizes
+        .then(|| product.sizes.iter().map(|size| normalize_size(size)).collect());
+
+    let mut page = state.page.lock().await.clone();
+    if !include_text_content {
+        page.text_content = None;
+    }
+    // The URL's region segment names the version actually requested.
+    if let Some(locale) = url_locale(&url) {
+        page.locale = Some(locale);
+    }
 
-    let result = py.allow_threads(|| {
-        tokio::runtime::Runtime::new()
-            .unwrap()
-            .block_on(scrape_product_rust(url, timeout_sec))
+    let quality_score = state.quality_score(&product, &page).await;
+    let diagnostics = if state.options.include_diagnostics {
+        let runs = state.approach_runs.lock().await;
+        let approaches: Vec<serde_json::Value> = runs
+            .iter()
+            .map(|run| {
+                serde_json::json!({
+                    "name": run.name,
+                    "success": run.success,
+                    "status": run.status,
+                    "elapsed_ms": run.elapsed_ms,
+                })
+            })
+            .collect();
+        Some(serde_json::json!({
+            "approaches": approaches,
+            "total_elapsed_ms": state.elapsed_ms() as u64,
+            "timed_out": global_timeout_fired,
+        }))
+    } else {
+        None
+    };
+    let images_detailed = state.options.include_image_details.then(|| {
+        product
+            .image_urls
+            .iter()
+            .map(|src| (src.clone(), page.image_alts.get(src).cloned()))
+            .collect()
     });
-    match result {
-        Ok(product) => {
-            // Compute missing flags + unsupported before moving fields out of `product`
-            let name_missing = product.product_name.is_none();
-            let brand_missing = product.brand.is_none();
-            let price_missing = product
-                .price
-                .as_ref()
-                .and_then(|p| p.amount)
-                .is_none();
-            let image_missing = product.image_urls.is_empty();
-            let success = !(name_missing || brand_missing || price_missing || image_missing);
-            let unsupported = matches!(
-                product.garment_type.as_deref(),
-                Some("unsupported")
-            );
+    let images_variants = state.options.include_image_details.then(|| {
+        product
+            .image_urls
+            .iter()
+            .map(|src| {
+                let base = html_extractor::canonical_image_key(src);
+                let mut resolutions = page
+                    .image_variants
+                    .get(&base)
+                    .cloned()
+                    .unwrap_or_else(|| vec![(src.clone(), html_extractor::image_width_hint(src))]);
+                resolutions.sort_by_key(|(_, width)| std::cmp::Reverse(width.unwrap_or(0)));
+                (base, resolutions)
+            })
+            .collect()
+    });
+
+    // Every approach task is aborted by now, so this lock isn't contended.
+    let attribution = if include_attribution {
+        Some(state.field_attribution.lock().await.clone())
+    } else {
+        None
+    };
+
+    Ok(ScrapeOutcome {
+        product,
+        page,
+        bytes_downloaded: state.bytes_downloaded(),
+        completion_reason,
+        changes,
+        sizes_normalized,
+        currency_mismatch,
+        likely_mismatch,
+        diagnostics,
+        attribution,
+        quality_score,
+        images_detailed,
+        images_variants,
+    })
+}
 
-            let dict = PyDict::new_bound(py);
-            dict.set_item("product_name", product.product_name)?;
-            dict.set_item("brand", product.brand)?;
+/// Scrapes `url` with config from the environment (the same variables the
+/// Python bindings read) and default options, for embedding without Python.
+/// Must run inside a tokio runtime.
+pub async fn scrape_product(url: &str, timeout_secs: f64) -> Result<ProductData, ScrapeError> {
+    let ctx = ScraperContext::new(ScraperConfig::from_env())
+        .map_err(|e| ScrapeError::NetworkError(format!("failed to build http clients: {}", e)))?;
+    scrape_product_with_context(url, timeout_secs, &ctx).await
+}
 
-            if let Some(price) = product.price {
-                let price_dict = PyDict::new_bound(py);
-                price_dict.set_item("amount", price.amount)?;
-                price_dict.set_item("currency", price.currency)?;
-                dict.set_item("price", price_dict)?;
-            }
+/// `scrape_product` through an existing context, e.g. one built with
+/// `ScraperContext::with_backends` or reused across scrapes.
+pub async fn scrape_product_with_context(
+    url: &str,
+    timeout_secs: f64,
+    ctx: &ScraperContext,
+) -> Result<ProductData, ScrapeError> {
+    let options = ScrapeOptions {
+        timeout_sec: timeout_secs,
+        ..ScrapeOptions::default()
+    };
+    scrape_product_rust(url.to_string(), options, ctx.clone())
+        .await
+        .map(|outcome| outcome.product)
+        .map_err(|failure| failure.error)
+}
+
+// ==================== METRICS ====================
 
-            dict.set_item("image_urls", product.image_urls)?;
-            dict.set_item("garment_type", product.garment_type)?;
-            dict.set_item("availability", product.availability)?;
+struct ApproachMetric {
+    approach: String,
+    success: bool,
+    elapsed_ms: u128,
+}
 
-            // Missing flags + success (for debugging / benchmarking)
-            let missing_flags = PyDict::new_bound(py);
-            missing_flags.set_item("name_missing", name_missing)?;
-            missing_flags.set_item("brand_missing", brand_missing)?;
-            missing_flags.set_item("price_missing", price_missing)?;
-            missing_flags.set_item("image_missing", image_missing)?;
-            missing_flags.set_item("unsupported", unsupported)?;
-            dict.set_item("missing_flags", missing_flags)?;
-            dict.set_item("success", success)?;
+lazy_static! {
+    /// Feeds the dispatcher thread that calls the hook; None while no hook is set.
+    static ref METRICS_QUEUE: std::sync::Mutex<Option<std::sync::mpsc::Sender<ApproachMetric>>> =
+        std::sync::Mutex::new(None);
+}
 
-            Ok(dict.into())
+/// Queues an approach result for the metrics hook. Never blocks on the GIL: the
+/// hook runs on a dedicated thread.
+fn emit_approach_metric(approach: &str, success: bool, elapsed_ms: u128) {
+    if let Ok(queue) = METRICS_QUEUE.lock() {
+        if let Some(sender) = queue.as_ref() {
+            let _ = sender.send(ApproachMetric {
+                approach: approach.to_string(),
+                success,
+                elapsed_ms,
+            });
         }
-        Err(e) => Err(pyo3::exceptions::PyRuntimeError::new_err(e)),
     }
 }
 
-#[pymodule]
-fn rust_scraper(m: &Bound<'_, PyModule>) -> PyResult<()> {
-    m.add_function(wrap_pyfunction!(scrape_url, m)?)?;
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rescrape_against_previous_output_has_no_changes() {
+        let scrape = || {
+            let mut product = ProductData {
+                product_name: Some("Tee".to_string()),
+                colors: vec!["Navy".to_string()],
+                // Results always carry on_sale as a bool.
+                on_sale: Some(false),
+                ..Default::default()
+            };
+            product.fill_selected_color();
+            product
+        };
+        let baseline = serde_json::to_value(scrape()).unwrap();
+        assert_eq!(baseline["selected_color"], "Navy");
+        assert!(scrape().changes_since(&baseline).is_empty());
+    }
+
+    #[test]
+    fn parses_swiss_apostrophe_thousands() {
+        let price = parse_price_string("CHF 1'299.00");
+        assert_eq!(price.amount, Some(1299));
+        assert_eq!(price.amount_cents, Some(129_900));
+        assert_eq!(price.currency.as_deref(), Some("CHF"));
+    }
+
+    #[test]
+    fn parses_price_currency_symbols_and_codes() {
+        let cases: &[(&str, i64, &str)] = &[
+            ("$19.99", 1999, "USD"),
+            ("€49.99", 4999, "EUR"),
+            ("£20", 2000, "GBP"),
+            ("A$59.95", 5995, "AUD"),
+            ("R$ 199.90", 19990, "BRL"),
+            ("299 kr", 29900, "SEK"),
+            ("1299 kr NOK", 129900, "NOK"),
+            ("149 zł", 14900, "PLN"),
+            ("₩39000", 3900000, "KRW"),
+            ("₺899.90", 89990, "TRY"),
+            ("CHF 89.00", 8900, "CHF"),
+            ("฿1290", 129000, "THB"),
+        ];
+        for &(raw, cents, currency) in cases {
+            let price = parse_price_string(raw);
+            assert_eq!(price.amount_cents, Some(cents), "{}", raw);
+            assert_eq!(price.currency.as_deref(), Some(currency), "{}", raw);
+        }
+    }
+
+    #[test]
+    fn normalizes_currency_codes_and_symbols() {
+        assert_eq!(normalize_currency_code("usd").as_deref(), Some("USD"));
+        assert_eq!(normalize_currency_code("$").as_deref(), Some("USD"));
+        assert_eq!(normalize_currency_code("Eur").as_deref(), Some("EUR"));
+        assert_eq!(normalize_currency_code("GBP ").as_deref(), Some("GBP"));
+        assert_eq!(normalize_currency_code("dollars"), None);
+        assert_eq!(normalize_currency_code(""), None);
+    }
+
+    #[test]
+    fn microdata_content_price_is_a_structured_price() {
+        let extracted = serde_json::json!({
+            "microdata_price": { "price": "49.99", "currency": "eur" }
+        });
+        let price = structured_data_price(&extracted).unwrap();
+        assert_eq!(price.amount_cents, Some(4999));
+        assert_eq!(price.currency.as_deref(), Some("EUR"));
+    }
+
+    #[test]
+    fn select_current_price_skips_discounts_and_other_currencies() {
+        let current = |s: &str| select_current_price(&price_candidates(s)).map(|p| (p.amount_cents, p.currency));
+        let usd = |cents| Some((Some(cents), Some("USD".to_string())));
+        assert_eq!(current("Now $25 Was $50"), usd(2500));
+        assert_eq!(current("$25.00 $50.00"), usd(2500));
+        assert_eq!(current("Save $10 Now $40"), usd(4000));
+        assert_eq!(current("$5 off $45.00"), usd(4500));
+        assert_eq!(current("€30.00 (approx. $20)"), Some((Some(3000), Some("EUR".to_string()))));
+        assert_eq!(current("Save $10"), None);
+    }
+
+    #[test]
+    fn clean_product_url_keeps_variant_params() {
+        assert_eq!(
+            clean_product_url(
+                "https://www.store.com/p/jacket/123.html?dwvar_123_color=BLK&dwvar_123_size=M&cgid=sale&utm_source=ads"
+            ),
+            "https://www.store.com/p/jacket/123.html?dwvar_123_color=BLK&dwvar_123_size=M"
+        );
+        assert_eq!(
+            clean_product_url("https://shop.myshopify.com/products/tee?variant=4242&gclid=abc&srsltid=xyz"),
+            "https://shop.myshopify.com/products/tee?variant=4242"
+        );
+        assert_eq!(
+            clean_product_url("https://shop.com/products/tee?ref=home&fbclid=1"),
+            "https://shop.com/products/tee"
+        );
+    }
+
+    #[test]
+    fn urls_differing_only_by_session_token_match() {
+        assert!(urls_match_product(
+            "https://shop.com/s/ABC123/products/jacket",
+            "https://shop.com/s/XYZ789/products/jacket"
+        ));
+        assert!(urls_match_product(
+            "https://shop.com/products/jacket;jsessionid=A1B2C3",
+            "https://shop.com/products/jacket"
+        ));
+        assert!(urls_match_product(
+            "https://shop.com/sid=4f2a/products/jacket",
+            "https://shop.com/products/jacket"
+        ));
+        assert!(urls_match_product(
+            "https://shop.com/0123456789abcdef0123/products/jacket",
+            "https://shop.com/products/jacket"
+        ));
+        assert!(!urls_match_product(
+            "https://shop.com/s/ABC123/products/jacket",
+            "https://shop.com/s/ABC123/products/coat"
+        ));
+        assert!(!urls_match_product(
+            "https://shop.com/s/ABC123/products/jacket",
+            "https://other.com/s/ABC123/products/jacket"
+        ));
+    }
+
+    /// gzip of `<html><body><h1>Gzipped tee</h1></body></html>`.
+    const GZIPPED_PAGE: &[u8] = &[
+        0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0xb3, 0xc9, 0x28, 0xc9, 0xcd, 0xb1, 0xb3, 0x49,
+        0xca, 0x4f, 0xa9, 0xb4, 0xb3, 0xc9, 0x30, 0xb4, 0x73, 0xaf, 0xca, 0x2c, 0x28, 0x48, 0x4d, 0x51, 0x28, 0x49,
+        0x4d, 0xb5, 0xd1, 0x07, 0xf2, 0x6d, 0xf4, 0x21, 0x52, 0xfa, 0x60, 0x75, 0x00, 0xcb, 0xb6, 0xc9, 0x07, 0x2e,
+        0x00, 0x00, 0x00,
+    ];
+
+    #[tokio::test]
+    async fn read_html_decodes_gzip_bodies() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Served undecoded, the bytes are rejected rather than parsed.
+        assert!(looks_binary(GZIPPED_PAGE));
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = [0u8; 4096];
+            let _ = socket.read(&mut request).await;
+            let head = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Encoding: gzip\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                GZIPPED_PAGE.len()
+            );
+            socket.write_all(head.as_bytes()).await.unwrap();
+            socket.write_all(GZIPPED_PAGE).await.unwrap();
+        });
+
+        let client = wreq::Client::builder().gzip(true).deflate(true).brotli(true).build().unwrap();
+        let resp = client
+            .get(format!("http://{}/", addr))
+            .header("Accept-Encoding", ACCEPT_ENCODING)
+            .send()
+            .await
+            .unwrap();
+        let state = ScrapeState::new(ScrapeOptions::default(), Arc::new(ScraperConfig::from_env()));
+        let html = read_html(resp, &state, "test").await;
+        assert_eq!(html.as_deref(), Some("<html><body><h1>Gzipped tee</h1></body></html>"));
+    }
+
+    struct StubFetcher;
+
+    impl HtmlFetcher for StubFetcher {
+        fn fetch<'a>(
+            &'a self,
+            _route: FetchRoute,
+            _url: &'a str,
+            _state: &'a ScrapeState,
+            _ctx: &'a ScraperContext,
+        ) -> BoxFuture<'a, Option<String>> {
+            let html = r#"<html><head><script type="application/ld+json">
+                {"@type": "Product", "name": "Linen Shirt", "brand": {"@type": "Brand", "name": "Acme"},
+                 "image": ["https://cdn.example.com/linen-shirt.jpg"],
+                 "offers": {"@type": "Offer", "price": "49.00", "priceCurrency": "USD"}}
+                </script></head><body><h1>Linen Shirt</h1></body></html>"#;
+            futures::future::ready(Some(html.to_string())).boxed()
+        }
+    }
+
+    struct StubClassifier;
+
+    impl ProductClassifier for StubClassifier {
+        fn extract<'a>(
+            &'a self,
+            _url: &'a str,
+            _data: &'a serde_json::Value,
+            _state: &'a ScrapeState,
+            _ctx: &'a ScraperContext,
+        ) -> BoxFuture<'a, Option<HashMap<String, serde_json::Value>>> {
+            let fields = HashMap::from([
+                ("garment_type".to_string(), serde_json::json!("upper")),
+                ("gender".to_string(), serde_json::json!("male")),
+            ]);
+            futures::future::ready(Some(fields)).boxed()
+        }
+
+        fn classify_url<'a>(
+            &'a self,
+            _url: &'a str,
+            _state: &'a ScrapeState,
+            _ctx: &'a ScraperContext,
+        ) -> BoxFuture<'a, Option<HashMap<String, serde_json::Value>>> {
+            futures::future::ready(None).boxed()
+        }
+
+        fn classify_title<'a>(
+            &'a self,
+            _url: &'a str,
+            _title: &'a str,
+            _snippet: Option<&'a str>,
+            _state: &'a ScrapeState,
+            _ctx: &'a ScraperContext,
+        ) -> BoxFuture<'a, Option<HashMap<String, serde_json::Value>>> {
+            futures::future::ready(None).boxed()
+        }
+    }
+
+    #[tokio::test]
+    async fn custom_backends_feed_the_merge() {
+        let config = ScraperConfig {
+            mock_fixtures_dir: None,
+            fetch_jitter_ms: None,
+            validate_image_urls: false,
+            min_image_edge: None,
+            ..ScraperConfig::from_env()
+        };
+        let ctx = ScraperContext::with_backends(config, Arc::new(StubFetcher), Arc::new(StubClassifier)).unwrap();
+        let options = ScrapeOptions {
+            timeout_sec: 5.0,
+            enabled_approaches: Some(vec!["curlcffi_gemini".to_string()]),
+            ..ScrapeOptions::default()
+        };
+
+        let outcome = scrape_product_rust("https://shop.example/products/linen-shirt".to_string(), options, ctx)
+            .await
+            .unwrap();
+        let product = &outcome.product;
+        assert_eq!(outcome.completion_reason, CompletionReason::StrongComplete);
+        assert_eq!(product.product_name.as_deref(), Some("Linen Shirt"));
+        assert_eq!(product.brand.as_deref(), Some("Acme"));
+        let price = product.price.as_ref().unwrap();
+        assert_eq!(price.amount_cents, Some(4900));
+        assert_eq!(price.currency.as_deref(), Some("USD"));
+        assert_eq!(product.image_urls, vec!["https://cdn.example.com/linen-shirt.jpg"]);
+        assert_eq!(product.garment_type.as_deref(), Some("upper"));
+        assert_eq!(product.gender.as_deref(), Some("male"));
+    }
+
+    #[tokio::test]
+    async fn mock_fixtures_drive_a_scrape_offline() {
+        let config = ScraperConfig {
+            genai_api_key: Some("test-key".to_string()),
+            serpapi_key: None,
+            cloudflare_worker_url: None,
+            proxy_urls: Vec::new(),
+            fetch_jitter_ms: None,
+            mock_fixtures_dir: Some(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures").to_string()),
+            ..ScraperConfig::from_env()
+        };
+        let ctx = ScraperContext::new(config).unwrap();
+        let options = ScrapeOptions {
+            timeout_sec: 5.0,
+            ..ScrapeOptions::default()
+        };
+
+        let outcome = scrape_product_rust("https://shop.example/p/linen-shirt".to_string(), options, ctx)
+            .await
+            .unwrap();
+        let product = &outcome.product;
+        assert_eq!(outcome.completion_reason, CompletionReason::StrongComplete);
+        assert_eq!(product.product_name.as_deref(), Some("Linen Shirt"));
+        assert_eq!(product.brand.as_deref(), Some("Acme"));
+        let price = product.price.as_ref().unwrap();
+        assert_eq!(price.amount_cents, Some(4900));
+        assert_eq!(price.currency.as_deref(), Some("USD"));
+        assert_eq!(
+            product.image_urls,
+            vec![
+                "https://cdn.shop.example/linen-shirt-front.jpg",
+                "https://cdn.shop.example/linen-shirt-back.jpg",
+            ]
+        );
+        assert_eq!(product.garment_type.as_deref(), Some("upper"));
+        assert_eq!(product.gender.as_deref(), Some("male"));
+        assert_eq!(product.color.as_deref(), Some("Natural"));
+        assert_eq!(product.sizes, vec!["S", "M", "L"]);
+    }
+
+    #[test]
+    fn jitter_range_is_clamped() {
+        assert_eq!(parse_jitter_range("100,400"), Some((100, 400)));
+        assert_eq!(parse_jitter_range("400, 100"), Some((100, 400)));
+        assert_eq!(parse_jitter_range("250"), Some((250, 250)));
+        assert_eq!(
+            parse_jitter_range(&format!("0,{}", u64::MAX)),
+            Some((0, MAX_FETCH_JITTER_MS))
+        );
+        assert_eq!(parse_jitter_range("1,2,3"), None);
+    }
+
+    #[test]
+    fn plausible_price_ceiling_is_in_minor_units() {
+        let state = ScrapeState::new(ScrapeOptions::default(), Arc::new(ScraperConfig::from_env()));
+        let usd = |amount: f64| Price::from_decimal(amount, Some("USD".to_string()));
+        assert!(state.plausible_price(&usd(99.99), "price", "test"));
+        assert!(state.plausible_price(&usd(100_000.0), "price", "test"));
+        assert!(!state.plausible_price(&usd(5_000_000.0), "price", "test"));
+        assert!(!state.plausible_price(&usd(0.0), "price", "test"));
+    }
+
+    #[test]
+    fn fetch_cache_keeps_query_distinct_products_apart() {
+        assert_ne!(
+            fetch_cache_key("https://shop.com/product.php?id=1"),
+            fetch_cache_key("https://shop.com/product.php?id=2")
+        );
+        assert_ne!(
+            fetch_cache_key("https://shop.com/products/tee?variant=1"),
+            fetch_cache_key("https://shop.com/products/tee?variant=2")
+        );
+        assert_eq!(
+            fetch_cache_key("https://shop.com/products/tee?variant=1&utm_source=ads#reviews"),
+            fetch_cache_key("https://shop.com/products/tee?variant=1")
+        );
+    }
 }
@@ -0,0 +1,79 @@
+use log::kv::{Error as KvError, Key, Source, Value as KvValue, VisitSource};
+use log::{LevelFilter, Log, Metadata, Record};
+use serde_json::{Map, Value};
+use std::sync::Once;
+
+use crate::env_var;
+
+/// `log` backend installed on module import. Lines keep the historical
+/// `[rust_scraper] ...` text by default; `LOG_FORMAT=json` prints one JSON
+/// object per line instead, with the record's key-values (url, source,
+/// elapsed_ms, bytes, ...) as top-level fields. If the host already installed
+/// a `log` logger (e.g. pyo3-log), that one keeps receiving the records.
+struct ScraperLogger {
+    json: bool,
+}
+
+impl Log for ScraperLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        if !self.json {
+            println!("[rust_scraper] {}", record.args());
+            return;
+        }
+
+        let mut fields = Map::new();
+        fields.insert("level".to_string(), Value::String(record.level().as_str().to_lowercase()));
+        fields.insert("message".to_string(), Value::String(record.args().to_string()));
+        let _ = record.key_values().visit(&mut JsonFields(&mut fields));
+        println!("{}", Value::Object(fields));
+    }
+
+    fn flush(&self) {}
+}
+
+struct JsonFields<'a>(&'a mut Map<String, Value>);
+
+impl<'kvs> VisitSource<'kvs> for JsonFields<'_> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: KvValue<'kvs>) -> Result<(), KvError> {
+        let json = if let Some(n) = value.to_u64() {
+            Value::from(n)
+        } else if let Some(n) = value.to_i64() {
+            Value::from(n)
+        } else if let Some(b) = value.to_bool() {
+            Value::Bool(b)
+        } else if let Some(f) = value.to_f64() {
+            Value::from(f)
+        } else {
+            Value::String(value.to_string())
+        };
+        self.0.insert(key.as_str().to_string(), json);
+        Ok(())
+    }
+}
+
+static INIT: Once = Once::new();
+
+/// Installs the logger once, at the level from `LOG_LEVEL` (default info).
+pub(crate) fn init() {
+    INIT.call_once(|| {
+        let json = env_var("LOG_FORMAT").is_some_and(|f| f.eq_ignore_ascii_case("json"));
+        let level = env_var("LOG_LEVEL")
+            .and_then(|level| parse_level(&level))
+            .unwrap_or(LevelFilter::Info);
+        if log::set_boxed_logger(Box::new(ScraperLogger { json })).is_ok() {
+            log::set_max_level(level);
+        }
+    });
+}
+
+/// "off" | "error" | "warn" | "info" | "debug" | "trace", any case.
+pub(crate) fn parse_level(level: &str) -> Option<LevelFilter> {
+    level.trim().parse().ok()
+}
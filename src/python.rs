@@ -0,0 +1,716 @@
+//! PyO3 bindings (the `python` feature): the `rust_scraper` module, the
+//! `Scraper` class, the scrape functions and the exception hierarchy. Result
+//! dicts are built here from the core's `ScrapeOutcome`.
+
+use lazy_static::lazy_static;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::{
+    extract_product_data_from_html, logging, normalize_currency_code, scrape_product_rust, ApproachMetric,
+    Price, ScrapeError, ScrapeFailure, ScrapeOptions, ScrapeOutcome, ScraperConfig, ScraperContext,
+    APPROACHES, METRICS_QUEUE,
+};
+
+lazy_static! {
+    /// Python callable registered via `set_metrics_hook`.
+    static ref METRICS_HOOK: std::sync::Mutex<Option<PyObject>> = std::sync::Mutex::new(None);
+}
+
+fn spawn_metrics_dispatcher() -> std::sync::mpsc::Sender<ApproachMetric> {
+    let (sender, receiver) = std::sync::mpsc::channel::<ApproachMetric>();
+    std::thread::spawn(move || {
+        // Exits once the hook is cleared and the sender dropped.
+        for metric in receiver {
+            Python::with_gil(|py| {
+                let hook = match METRICS_HOOK.lock() {
+                    Ok(hook) => hook.as_ref().map(|h| h.clone_ref(py)),
+                    Err(_) => None,
+                };
+                if let Some(hook) = hook {
+                    if let Err(e) = hook.call1(py, (metric.approach, metric.success, metric.elapsed_ms)) {
+                        log::warn!("metrics hook raised: {}", e);
+                    }
+                }
+            });
+        }
+    });
+    sender
+}
+
+fn json_to_py(py: Python, value: &serde_json::Value) -> PyResult<PyObject> {
+    Ok(match value {
+        serde_json::Value::Null => py.None(),
+        serde_json::Value::Bool(b) => b.into_py(py),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => i.into_py(py),
+            None => n.as_f64().into_py(py),
+        },
+        serde_json::Value::String(s) => s.into_py(py),
+        serde_json::Value::Array(items) => {
+            let list = pyo3::types::PyList::empty_bound(py);
+            for item in items {
+                list.append(json_to_py(py, item)?)?;
+            }
+            list.into()
+        }
+        serde_json::Value::Object(map) => {
+            let dict = PyDict::new_bound(py);
+            for (k, v) in map {
+                dict.set_item(k, json_to_py(py, v)?)?;
+            }
+            dict.into()
+        }
+    })
+}
+
+fn py_to_json(value: &Bound<'_, PyAny>) -> PyResult<serde_json::Value> {
+    if value.is_none() {
+        return Ok(serde_json::Value::Null);
+    }
+    // bool before int: Python bools are ints.
+    if let Ok(b) = value.downcast::<pyo3::types::PyBool>() {
+        return Ok(serde_json::Value::Bool(b.is_true()));
+    }
+    if let Ok(i) = value.extract::<i64>() {
+        return Ok(i.into());
+    }
+    if let Ok(f) = value.extract::<f64>() {
+        return Ok(f.into());
+    }
+    if let Ok(s) = value.extract::<String>() {
+        return Ok(serde_json::Value::String(s));
+    }
+    if let Ok(dict) = value.downcast::<PyDict>() {
+        let mut map = serde_json::Map::new();
+        for (k, v) in dict.iter() {
+            map.insert(k.str()?.to_string(), py_to_json(&v)?);
+        }
+        return Ok(serde_json::Value::Object(map));
+    }
+    if let Ok(items) = value.iter() {
+        return items
+            .map(|item| py_to_json(&item?))
+            .collect::<PyResult<Vec<_>>>()
+            .map(serde_json::Value::Array);
+    }
+    Err(pyo3::exceptions::PyTypeError::new_err(format!(
+        "cannot convert {} to JSON",
+        value.get_type().name()?
+    )))
+}
+
+/// Product fields found before a failed scrape, or None if nothing was found.
+fn partial_to_py(py: Python, failure: &ScrapeFailure) -> PyResult<PyObject> {
+    match &failure.partial {
+        Some(product) => {
+            let value = serde_json::to_value(product)
+                .map_err(|e| pyo3::exceptions::PyRuntimeError::new_err(e.to_string()))?;
+            json_to_py(py, &value)
+        }
+        None => Ok(py.None()),
+    }
+}
+
+/// Python exception classes for `ScrapeError`. All derive from `ScrapeError`,
+/// itself a `RuntimeError`, so `except RuntimeError` keeps working.
+mod py_errors {
+    use pyo3::create_exception;
+    use pyo3::exceptions::PyRuntimeError;
+
+    create_exception!(rust_scraper, ScrapeError, PyRuntimeError, "Base class for failed scrapes.");
+    create_exception!(rust_scraper, NotFashionProductError, ScrapeError);
+    create_exception!(rust_scraper, UnsupportedProductError, ScrapeError);
+    create_exception!(rust_scraper, LoginRequiredError, ScrapeError);
+    create_exception!(rust_scraper, CurrencyMismatchError, ScrapeError);
+    create_exception!(rust_scraper, ScrapeTimeoutError, ScrapeError);
+    create_exception!(rust_scraper, AllApproachesFailedError, ScrapeError);
+    create_exception!(rust_scraper, NetworkError, ScrapeError);
+    create_exception!(rust_scraper, RobotsDisallowedError, ScrapeError);
+}
+
+impl ScrapeError {
+    /// Name of the matching `py_errors` class, also reported by `scrape_many`.
+    fn py_type_name(&self) -> &'static str {
+        match self {
+            ScrapeError::NotFashionProduct { .. } => "NotFashionProductError",
+            ScrapeError::UnsupportedProduct { .. } => "UnsupportedProductError",
+            ScrapeError::LoginRequired { .. } => "LoginRequiredError",
+            ScrapeError::CurrencyMismatch { .. } => "CurrencyMismatchError",
+            ScrapeError::Timeout { .. } => "ScrapeTimeoutError",
+            ScrapeError::AllApproachesFailed { .. } => "AllApproachesFailedError",
+            ScrapeError::NetworkError(_) => "NetworkError",
+            ScrapeError::RobotsDisallowed { .. } => "RobotsDisallowedError",
+        }
+    }
+
+    fn to_py_err(&self) -> PyErr {
+        let message = self.to_string();
+        match self {
+            ScrapeError::NotFashionProduct { .. } => py_errors::NotFashionProductError::new_err(message),
+            ScrapeError::UnsupportedProduct { .. } => py_errors::UnsupportedProductError::new_err(message),
+            ScrapeError::LoginRequired { .. } => py_errors::LoginRequiredError::new_err(message),
+            ScrapeError::CurrencyMismatch { .. } => py_errors::CurrencyMismatchError::new_err(message),
+            ScrapeError::Timeout { .. } => py_errors::ScrapeTimeoutError::new_err(message),
+            ScrapeError::AllApproachesFailed { .. } => py_errors::AllApproachesFailedError::new_err(message),
+            ScrapeError::NetworkError(_) => py_errors::NetworkError::new_err(message),
+            ScrapeError::RobotsDisallowed { .. } => py_errors::RobotsDisallowedError::new_err(message),
+        }
+    }
+}
+
+/// The failure's exception class, carrying the partial product as its
+/// `partial_data` attribute.
+fn failure_to_py_err(py: Python, failure: ScrapeFailure) -> PyErr {
+    let partial = match partial_to_py(py, &failure) {
+        Ok(partial) => partial,
+        Err(e) => return e,
+    };
+    let err = failure.error.to_py_err();
+    if let Err(e) = err.value_bound(py).setattr("partial_data", partial) {
+        return e;
+    }
+    err
+}
+
+fn price_to_py<'py>(py: Python<'py>, price: &Price) -> PyResult<Bound<'py, PyDict>> {
+    let price_dict = PyDict::new_bound(py);
+    price_dict.set_item("amount", price.amount)?;
+    price_dict.set_item("amount_cents", price.amount_cents)?;
+    price_dict.set_item("currency", &price.currency)?;
+    Ok(price_dict)
+}
+
+fn outcome_to_py(py: Python, outcome: ScrapeOutcome) -> PyResult<PyObject> {
+    let ScrapeOutcome {
+        product,
+        page,
+        bytes_downloaded,
+        completion_reason,
+        changes,
+        sizes_normalized,
+        currency_mismatch,
+        likely_mismatch,
+        diagnostics,
+        attribution,
+        quality_score,
+        images_detailed,
+        images_variants,
+    } = outcome;
+    // Compute missing flags + unsupported before moving fields out of `product`
+    let (missing_flags, success) = product.status_flags();
+
+    let dict = PyDict::new_bound(py);
+    dict.set_item("product_name", product.product_name)?;
+    dict.set_item("brand", product.brand)?;
+
+    if let Some(price) = &product.price {
+        dict.set_item("price", price_to_py(py, price)?)?;
+    }
+    dict.set_item("price_raw", product.price_raw)?;
+
+    // The extractor trims its image list; report the gallery size before that.
+    let total_image_count = page.total_image_count.max(product.image_urls.len());
+    dict.set_item("image_urls", product.image_urls)?;
+    if let Some(images_detailed) = images_detailed {
+        let items = pyo3::types::PyList::empty_bound(py);
+        for (src, alt) in images_detailed {
+            let item = PyDict::new_bound(py);
+            item.set_item("src", src)?;
+            item.set_item("alt", alt)?;
+            items.append(item)?;
+        }
+        dict.set_item("images_detailed", items)?;
+    }
+    if let Some(images_variants) = images_variants {
+        let items = pyo3::types::PyList::empty_bound(py);
+        for (base, resolutions) in images_variants {
+            let item = PyDict::new_bound(py);
+            item.set_item("base", base)?;
+            let list = pyo3::types::PyList::empty_bound(py);
+            for (url, width) in resolutions {
+                let resolution = PyDict::new_bound(py);
+                resolution.set_item("url", url)?;
+                resolution.set_item("width", width)?;
+                list.append(resolution)?;
+            }
+            item.set_item("resolutions", list)?;
+            items.append(item)?;
+        }
+        dict.set_item("images_variants", items)?;
+    }
+    dict.set_item("total_image_count", total_image_count)?;
+    dict.set_item("garment_type", product.garment_type)?;
+    dict.set_item("availability", product.availability)?;
+    dict.set_item("price_gated", product.price_gated)?;
+    dict.set_item("gender", product.gender)?;
+    dict.set_item("sizes", product.sizes)?;
+    if let Some(sizes_normalized) = sizes_normalized {
+        dict.set_item("sizes_normalized", sizes_normalized)?;
+    }
+
+    if let Some(original_price) = &product.original_price {
+        dict.set_item("original_price", price_to_py(py, original_price)?)?;
+    }
+    dict.set_item("on_sale", product.on_sale.unwrap_or(false))?;
+    dict.set_item("release_date", product.release_date)?;
+    dict.set_item("shipping_info", product.shipping_info)?;
+    dict.set_item("fit", product.fit)?;
+    dict.set_item("color", product.color)?;
+    dict.set_item("colors", product.colors)?;
+    dict.set_item("selected_color", product.selected_color)?;
+    if !product.bundle_items.is_empty() {
+        let items = pyo3::types::PyList::empty_bound(py);
+        for item in &product.bundle_items {
+            let item_dict = PyDict::new_bound(py);
+            item_dict.set_item("name", &item.name)?;
+            item_dict.set_item("price", price_to_py(py, &item.price)?)?;
+            items.append(item_dict)?;
+        }
+        dict.set_item("bundle_items", items)?;
+        match &product.bundle_total {
+            Some(total) => dict.set_item("bundle_total", price_to_py(py, total)?)?,
+            None => dict.set_item("bundle_total", py.None())?,
+        }
+    }
+    match &product.size_chart {
+        Some(chart) => dict.set_item("size_chart", json_to_py(py, chart)?)?,
+        None => dict.set_item("size_chart", py.None())?,
+    }
+
+    // Missing flags + success (for debugging / benchmarking)
+    dict.set_item("missing_flags", json_to_py(py, &missing_flags)?)?;
+    dict.set_item("success", success)?;
+    dict.set_item("quality_score", quality_score)?;
+    dict.set_item("bytes_downloaded", bytes_downloaded)?;
+    dict.set_item("completion_reason", completion_reason.as_str())?;
+    dict.set_item("currency_mismatch", currency_mismatch)?;
+    dict.set_item("likely_mismatch", likely_mismatch)?;
+    dict.set_item("platform", page.platform.as_deref())?;
+    dict.set_item("had_structured_data", page.had_structured_data)?;
+    dict.set_item("locale", page.locale.as_deref())?;
+    dict.set_item(
+        "alternate_urls",
+        json_to_py(py, &serde_json::Value::Object(page.alternate_urls.clone()))?,
+    )?;
+    if let Some(attribution) = attribution {
+        dict.set_item("attribution", attribution)?;
+    }
+    if let Some(diagnostics) = diagnostics {
+        dict.set_item("diagnostics", json_to_py(py, &diagnostics)?)?;
+    }
+    if let Some(changes) = changes {
+        dict.set_item("changes", json_to_py(py, &serde_json::Value::Object(changes))?)?;
+    }
+
+    if let Some(text_content) = &page.text_content {
+        dict.set_item("text_content", json_to_py(py, text_content)?)?;
+    }
+
+    Ok(dict.into())
+}
+
+/// Renames the top-level keys of a result dict per `field_map`, keeping key
+/// order. A key mapped onto another key's name replaces that entry.
+fn apply_field_map(py: Python, result: PyObject, field_map: Option<&HashMap<String, String>>) -> PyResult<PyObject> {
+    let Some(field_map) = field_map.filter(|map| !map.is_empty()) else {
+        return Ok(result);
+    };
+    let dict = result.downcast_bound::<PyDict>(py)?;
+    let renamed = PyDict::new_bound(py);
+    for (key, value) in dict.iter() {
+        let name: String = key.extract()?;
+        match field_map.get(&name) {
+            Some(mapped) => renamed.set_item(mapped, value)?,
+            None => renamed.set_item(key, value)?,
+        }
+    }
+    Ok(renamed.into())
+}
+
+/// Builds `ScrapeOptions` from the keyword args shared by every scrape entry point.
+/// A value of None keeps the default; unknown names raise TypeError like Python would.
+fn scrape_options(timeout_secs: Option<f64>, kwargs: Option<&Bound<'_, PyDict>>) -> PyResult<ScrapeOptions> {
+    let mut options = ScrapeOptions::default();
+    if let Some(timeout_sec) = timeout_secs {
+        options.timeout_sec = timeout_sec;
+    }
+
+    let Some(kwargs) = kwargs else {
+        return Ok(options);
+    };
+    for (key, value) in kwargs.iter() {
+        let key: String = key.extract()?;
+        if value.is_none() {
+            continue;
+        }
+        match key.as_str() {
+            "include_text_content" => options.include_text_content = value.extract()?,
+            "deterministic_only" => options.deterministic_only = value.extract()?,
+            "baseline" => options.baseline = Some(py_to_json(&value)?),
+            "normalize_sizes" => options.normalize_sizes = value.extract()?,
+            "strict_currency" => options.strict_currency = value.extract()?,
+            "include_attribution" => options.include_attribution = value.extract()?,
+            "use_serpapi" => options.use_serpapi = value.extract()?,
+            "cache_size" => options.cache_size = value.extract()?,
+            "retry_with_proxy" => options.retry_with_proxy = value.extract()?,
+            "raise_on_unsupported" => options.raise_on_unsupported = value.extract()?,
+            "include_image_details" => options.include_image_details = value.extract()?,
+            "field_map" => options.field_map = Some(value.extract()?),
+            "include_diagnostics" => options.include_diagnostics = value.extract()?,
+            "respect_robots" => options.respect_robots = value.extract()?,
+            "expected_currency" => {
+                let code: String = value.extract()?;
+                let normalized = normalize_currency_code(&code).ok_or_else(|| {
+                    pyo3::exceptions::PyValueError::new_err(format!("unknown currency '{}'", code))
+                })?;
+                options.expected_currency = Some(normalized);
+            }
+            "primary_approach" => {
+                let name: String = value.extract()?;
+                if !APPROACHES.contains(&name.as_str()) {
+                    return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                        "unknown approach '{}', expected one of {:?}",
+                        name, APPROACHES
+                    )));
+                }
+                options.primary_approach = Some(name);
+            }
+            "enabled_approaches" => {
+                let names: Vec<String> = value.extract()?;
+                let unknown: Vec<&String> = names.iter().filter(|n| !APPROACHES.contains(&n.as_str())).collect();
+                if !unknown.is_empty() {
+                    return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                        "unknown approaches {:?}, expected names from {:?}",
+                        unknown, APPROACHES
+                    )));
+                }
+                options.enabled_approaches = Some(names);
+            }
+            _ => {
+                return Err(pyo3::exceptions::PyTypeError::new_err(format!(
+                    "got an unexpected keyword argument '{}'",
+                    key
+                )))
+            }
+        }
+    }
+    Ok(options)
+}
+
+lazy_static! {
+    /// Process-wide runtime shared by every `Scraper` and `scrape_url` call;
+    /// built on first use.
+    static ref SHARED_RUNTIME: std::sync::Mutex<Option<Arc<tokio::runtime::Runtime>>> =
+        std::sync::Mutex::new(None);
+}
+
+fn shared_runtime() -> PyResult<Arc<tokio::runtime::Runtime>> {
+    let mut shared = SHARED_RUNTIME
+        .lock()
+        .map_err(|e| pyo3::exceptions::PyRuntimeError::new_err(e.to_string()))?;
+    if let Some(runtime) = shared.as_ref() {
+        return Ok(runtime.clone());
+    }
+    // Runtime creation can fail under fd/thread exhaustion; surface that as a
+    // Python exception rather than a panic. A failed attempt is retried next call.
+    let runtime = tokio::runtime::Runtime::new().map_err(|e| {
+        pyo3::exceptions::PyRuntimeError::new_err(format!(
+            "rust_scraper: failed to create tokio runtime (possible file descriptor or thread exhaustion): {}",
+            e
+        ))
+    })?;
+    let runtime = Arc::new(runtime);
+    *shared = Some(runtime.clone());
+    Ok(runtime)
+}
+
+/// Reusable scraper: holds the HTTP clients and config so repeated scrapes
+/// don't re-read env vars or rebuild clients. Usable as a context manager.
+#[pyclass(module = "rust_scraper")]
+struct Scraper {
+    runtime: Arc<tokio::runtime::Runtime>,
+    ctx: ScraperContext,
+}
+
+impl Scraper {
+    fn with_config(config: ScraperConfig) -> PyResult<Self> {
+        let runtime = shared_runtime()?;
+        let ctx = ScraperContext::new(config).map_err(|e| {
+            pyo3::exceptions::PyRuntimeError::new_err(format!(
+                "rust_scraper: failed to build http clients: {}",
+                e
+            ))
+        })?;
+        Ok(Self { runtime, ctx })
+    }
+}
+
+#[pymethods]
+impl Scraper {
+    /// Any argument left as None falls back to the matching environment variable.
+    #[new]
+    #[pyo3(signature = (
+        genai_api_key=None,
+        serpapi_key=None,
+        proxy_url=None,
+        cloudflare_worker_url=None,
+        gemini_model=None,
+        gemini_classification_model=None
+    ))]
+    fn new(
+        genai_api_key: Option<String>,
+        serpapi_key: Option<String>,
+        proxy_url: Option<String>,
+        cloudflare_worker_url: Option<String>,
+        gemini_model: Option<String>,
+        gemini_classification_model: Option<String>,
+    ) -> PyResult<Self> {
+        let defaults = ScraperConfig::from_env();
+        let config = ScraperConfig {
+            genai_api_key: genai_api_key.or(defaults.genai_api_key),
+            serpapi_key: serpapi_key.or(defaults.serpapi_key),
+            proxy_urls: proxy_url.map(|url| vec![url]).unwrap_or(defaults.proxy_urls),
+            cloudflare_worker_url: cloudflare_worker_url.or(defaults.cloudflare_worker_url),
+            gemini_model: gemini_model.unwrap_or(defaults.gemini_model),
+            gemini_classification_model: gemini_classification_model
+                .unwrap_or(defaults.gemini_classification_model),
+            ..defaults
+        };
+        Self::with_config(config)
+    }
+
+    #[pyo3(signature = (url, timeout_secs=None, **options))]
+    fn scrape(
+        &self,
+        py: Python,
+        url: String,
+        timeout_secs: Option<f64>,
+        options: Option<&Bound<'_, PyDict>>,
+    ) -> PyResult<PyObject> {
+        let options = scrape_options(timeout_secs, options)?;
+        let field_map = options.field_map.clone();
+        let ctx = self.ctx.clone();
+
+        let result = py.allow_threads(|| {
+            self.runtime
+                .block_on(scrape_product_rust(url, options, ctx))
+        });
+        match result {
+            Ok(outcome) => apply_field_map(py, outcome_to_py(py, outcome)?, field_map.as_ref()),
+            Err(failure) => Err(failure_to_py_err(py, failure)),
+        }
+    }
+
+    /// Scrapes all URLs concurrently, at most `concurrency` at a time (default:
+    /// all at once). Per-URL failures are returned inline as
+    /// `{"url": ..., "error": ...}` instead of raising.
+    #[pyo3(signature = (urls, timeout_secs=None, concurrency=None, **options))]
+    fn scrape_many(
+        &self,
+        py: Python,
+        urls: Vec<String>,
+        timeout_secs: Option<f64>,
+        concurrency: Option<usize>,
+        options: Option<&Bound<'_, PyDict>>,
+    ) -> PyResult<Vec<PyObject>> {
+        let options = scrape_options(timeout_secs, options)?;
+        if concurrency == Some(0) {
+            return Err(pyo3::exceptions::PyValueError::new_err("concurrency must be at least 1"));
+        }
+        let permits = Arc::new(tokio::sync::Semaphore::new(
+            concurrency.unwrap_or(urls.len()).max(1),
+        ));
+
+        let results = py.allow_threads(|| {
+            self.runtime.block_on(futures::future::join_all(urls.iter().map(|url| {
+                let permits = permits.clone();
+                let scrape = scrape_product_rust(url.clone(), options.clone(), self.ctx.clone());
+                async move {
+                    // Never closed, so acquire can't fail.
+                    let _permit = permits.acquire().await;
+                    scrape.await
+                }
+            })))
+        });
+
+        urls.into_iter()
+            .zip(results)
+            .map(|(url, result)| match result {
+                Ok(outcome) => apply_field_map(py, outcome_to_py(py, outcome)?, options.field_map.as_ref()),
+                Err(failure) => {
+                    let dict = PyDict::new_bound(py);
+                    dict.set_item("url", url)?;
+                    dict.set_item("partial_data", partial_to_py(py, &failure)?)?;
+                    dict.set_item("error", failure.error.to_string())?;
+                    dict.set_item("error_type", failure.error.py_type_name())?;
+                    Ok(dict.into())
+                }
+            })
+            .collect()
+    }
+
+    fn __enter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    #[pyo3(signature = (*_args))]
+    fn __exit__(&self, _args: &Bound<'_, pyo3::types::PyTuple>) -> bool {
+        false
+    }
+}
+
+lazy_static! {
+    /// Env-configured `Scraper` behind the module-level functions; built on first use.
+    static ref DEFAULT_SCRAPER: std::sync::Mutex<Option<Arc<Scraper>>> = std::sync::Mutex::new(None);
+}
+
+/// The shared env-configured `Scraper`, so `scrape_url` and friends read the
+/// env vars and build the HTTP clients once per process. A failed build is
+/// retried on the next call.
+fn default_scraper() -> PyResult<Arc<Scraper>> {
+    let mut shared = DEFAULT_SCRAPER
+        .lock()
+        .map_err(|e| pyo3::exceptions::PyRuntimeError::new_err(e.to_string()))?;
+    if let Some(scraper) = shared.as_ref() {
+        return Ok(scraper.clone());
+    }
+    let scraper = Arc::new(Scraper::with_config(ScraperConfig::from_env())?);
+    *shared = Some(scraper.clone());
+    Ok(scraper)
+}
+
+#[pyfunction]
+#[pyo3(signature = (url, timeout_secs=None, **options))]
+fn scrape_url(
+    py: Python,
+    url: String,
+    timeout_secs: Option<f64>,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<PyObject> {
+    default_scraper()?.scrape(py, url, timeout_secs, options)
+}
+
+/// Like `scrape_url`, but returns `ProductData::to_json`: the product fields
+/// plus missing_flags and success as one JSON object string, ready to write
+/// to a file or diff against a golden fixture. Failures raise as usual.
+#[pyfunction]
+#[pyo3(signature = (url, timeout_secs=None, **options))]
+fn scrape_url_json(
+    py: Python,
+    url: String,
+    timeout_secs: Option<f64>,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<String> {
+    let scraper = default_scraper()?;
+    let options = scrape_options(timeout_secs, options)?;
+    let ctx = scraper.ctx.clone();
+
+    let result = py.allow_threads(|| {
+        scraper
+            .runtime
+            .block_on(scrape_product_rust(url, options, ctx))
+    });
+    match result {
+        Ok(outcome) => Ok(outcome.product.to_json()),
+        Err(failure) => Err(failure_to_py_err(py, failure)),
+    }
+}
+
+/// Batch version of `scrape_url`: the shared default `Scraper` for every URL,
+/// at most `concurrency` scrapes in flight. Results come back in input order
+/// with failures inline, as with `Scraper.scrape_many`.
+#[pyfunction]
+#[pyo3(signature = (urls, timeout_secs=None, concurrency=None, **options))]
+fn scrape_urls(
+    py: Python,
+    urls: Vec<String>,
+    timeout_secs: Option<f64>,
+    concurrency: Option<usize>,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<Vec<PyObject>> {
+    default_scraper()?.scrape_many(py, urls, timeout_secs, concurrency, options)
+}
+
+/// Runs only the HTML extractor on supplied markup: no network, no Gemini.
+/// Returns the intermediate extractor JSON (structured_data, price_signals,
+/// images, content, ...) the LLM would otherwise be prompted with.
+#[pyfunction]
+#[pyo3(signature = (url, html))]
+fn extract_from_html(py: Python, url: String, html: String) -> PyResult<PyObject> {
+    let extracted = py.allow_threads(|| extract_product_data_from_html(&url, &html));
+    json_to_py(py, &extracted)
+}
+
+/// Sets the minimum level of the crate's log lines ("off", "error", "warn",
+/// "info", "debug", "trace"); defaults to LOG_LEVEL, else "info".
+#[pyfunction]
+#[pyo3(signature = (level))]
+fn set_log_level(level: &str) -> PyResult<()> {
+    let filter = logging::parse_level(level).ok_or_else(|| {
+        pyo3::exceptions::PyValueError::new_err(format!(
+            "unknown log level '{}', expected off, error, warn, info, debug or trace",
+            level
+        ))
+    })?;
+    logging::init();
+    log::set_max_level(filter);
+    Ok(())
+}
+
+/// Registers `hook(approach_name, success, elapsed_ms)`, called after every
+/// approach finishes. Runs on a background thread; pass None to unregister.
+#[pyfunction]
+#[pyo3(signature = (hook))]
+fn set_metrics_hook(hook: Option<&Bound<'_, PyAny>>) -> PyResult<()> {
+    if let Some(hook) = hook {
+        if !hook.is_callable() {
+            return Err(pyo3::exceptions::PyTypeError::new_err("metrics hook must be callable"));
+        }
+    }
+
+    let mut registered = METRICS_HOOK
+        .lock()
+        .map_err(|e| pyo3::exceptions::PyRuntimeError::new_err(e.to_string()))?;
+    let mut queue = METRICS_QUEUE
+        .lock()
+        .map_err(|e| pyo3::exceptions::PyRuntimeError::new_err(e.to_string()))?;
+    match hook {
+        Some(hook) => {
+            *registered = Some(hook.clone().unbind());
+            if queue.is_none() {
+                *queue = Some(spawn_metrics_dispatcher());
+            }
+        }
+        None => {
+            *registered = None;
+            *queue = None;
+        }
+    }
+    Ok(())
+}
+
+#[pymodule]
+fn rust_scraper(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    logging::init();
+    m.add_class::<Scraper>()?;
+    m.add_function(wrap_pyfunction!(scrape_url, m)?)?;
+    m.add_function(wrap_pyfunction!(scrape_url_json, m)?)?;
+    m.add_function(wrap_pyfunction!(scrape_urls, m)?)?;
+    m.add_function(wrap_pyfunction!(extract_from_html, m)?)?;
+    m.add_function(wrap_pyfunction!(set_metrics_hook, m)?)?;
+    m.add_function(wrap_pyfunction!(set_log_level, m)?)?;
+    let py = m.py();
+    m.add("ScrapeError", py.get_type_bound::<py_errors::ScrapeError>())?;
+    m.add("NotFashionProductError", py.get_type_bound::<py_errors::NotFashionProductError>())?;
+    m.add("UnsupportedProductError", py.get_type_bound::<py_errors::UnsupportedProductError>())?;
+    m.add("LoginRequiredError", py.get_type_bound::<py_errors::LoginRequiredError>())?;
+    m.add("CurrencyMismatchError", py.get_type_bound::<py_errors::CurrencyMismatchError>())?;
+    m.add("ScrapeTimeoutError", py.get_type_bound::<py_errors::ScrapeTimeoutError>())?;
+    m.add("AllApproachesFailedError", py.get_type_bound::<py_errors::AllApproachesFailedError>())?;
+    m.add("NetworkError", py.get_type_bound::<py_errors::NetworkError>())?;
+    m.add("RobotsDisallowedError", py.get_type_bound::<py_errors::RobotsDisallowedError>())?;
+    Ok(())
+}
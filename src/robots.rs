@@ -0,0 +1,137 @@
+/// Allow/Disallow rules from a robots.txt, for the group that applies to us:
+/// a group whose `User-agent` is our product token (`ROBOTS_PRODUCT_TOKEN`,
+/// any case) wins over `User-agent: *`, even when it has no rules. The browser
+/// UA we emulate is deliberately not matched, so rules meant for real browsers
+/// or e.g. Googlebot-Android don't apply. Matching follows RFC 9309: the
+/// longest matching pattern decides, Allow wins a tie, `*` matches any run and
+/// a trailing `$` anchors the end.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RobotsRules {
+    /// `(allow, pattern)`
+    rules: Vec<(bool, String)>,
+}
+
+impl RobotsRules {
+    /// Every path disallowed, for a robots.txt that couldn't be fetched (5xx
+    /// or network error): RFC 9309 treats the site as unreachable.
+    pub(crate) fn disallow_all() -> Self {
+        Self { rules: vec![(false, "/".to_string())] }
+    }
+
+    pub(crate) fn parse(body: &str, product_token: &str) -> Self {
+        let mut specific: Vec<(bool, String)> = Vec::new();
+        let mut wildcard: Vec<(bool, String)> = Vec::new();
+        let mut has_specific = false;
+
+        // Agents of the group being read; a rule line after agent lines closes
+        // the agent list, so the next User-agent starts a new group.
+        let mut agents: Vec<String> = Vec::new();
+        let mut names_us = false;
+        let mut in_rules = false;
+        for line in body.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let key = key.trim().to_lowercase();
+            let value = value.trim();
+            match key.as_str() {
+                "user-agent" => {
+                    if in_rules {
+                        agents.clear();
+                        names_us = false;
+                        in_rules = false;
+                    }
+                    if value.eq_ignore_ascii_case(product_token) {
+                        // Our group applies even if it only has `Disallow:`.
+                        names_us = true;
+                        has_specific = true;
+                    }
+                    agents.push(value.to_lowercase());
+                }
+                "allow" | "disallow" => {
+                    in_rules = true;
+                    // An empty Disallow allows everything; an empty Allow is a no-op.
+                    if value.is_empty() {
+                        continue;
+                    }
+                    let rule = (key == "allow", value.to_string());
+                    if names_us {
+                        specific.push(rule.clone());
+                    }
+                    if agents.iter().any(|agent| agent == "*") {
+                        wildcard.push(rule);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Self { rules: if has_specific { specific } else { wildcard } }
+    }
+
+    /// `path` is the URL path plus its `?query`, if any.
+    pub(crate) fn is_allowed(&self, path: &str) -> bool {
+        self.rules
+            .iter()
+            .filter(|(_, pattern)| pattern_matches(pattern, path))
+            .max_by_key(|(allow, pattern)| (pattern.len(), *allow))
+            .map_or(true, |(allow, _)| *allow)
+    }
+}
+
+fn pattern_matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(rest) => (rest, true),
+        None => (pattern, false),
+    };
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    let Some(mut rest) = path.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    for (i, part) in parts.iter().enumerate() {
+        // The last piece of an anchored pattern has to end the path.
+        if anchored && i == parts.len() - 1 {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    !anchored || rest.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOKEN: &str = "rust_scraper";
+
+    #[test]
+    fn empty_disallow_in_our_group_beats_the_wildcard() {
+        let rules = RobotsRules::parse("User-agent: Rust_Scraper\nDisallow:\n\nUser-agent: *\nDisallow: /\n", TOKEN);
+        assert!(rules.is_allowed("/products/tee"));
+        let rules = RobotsRules::parse("User-agent: *\nDisallow: /\n", TOKEN);
+        assert!(!rules.is_allowed("/products/tee"));
+    }
+
+    #[test]
+    fn browser_groups_dont_apply_to_us() {
+        let body = "User-agent: Googlebot-Android\nDisallow: /\n\nUser-agent: Chrome\nDisallow: /\n\nUser-agent: *\nDisallow: /cart\n";
+        let rules = RobotsRules::parse(body, TOKEN);
+        assert!(rules.is_allowed("/products/tee"));
+        assert!(!rules.is_allowed("/cart/checkout"));
+    }
+
+    #[test]
+    fn longest_match_wins_and_allow_wins_ties() {
+        let body = "User-agent: *\nDisallow: /p/\nAllow: /p/*.html$\nDisallow: /q\nAllow: /q\n";
+        let rules = RobotsRules::parse(body, TOKEN);
+        assert!(rules.is_allowed("/p/tee.html"));
+        assert!(!rules.is_allowed("/p/tee.html?x=1"));
+        assert!(rules.is_allowed("/q/anything"));
+    }
+}